@@ -1,12 +1,23 @@
 //! Main logic for the app
 use crate::commands::Function;
-use crate::{app::tile::ExtSender, clipboard::ClipBoardContentType};
+use crate::{
+    app::{
+        apps::{App, AppCommand},
+        pages::generic::{InputValue, View},
+        tile::ExtSender,
+    },
+    clipboard::ClipBoardContentType,
+    config::Config,
+    shell::ShellEvent,
+};
 
 pub mod apps;
 pub mod menubar;
 pub mod pages;
+pub mod providers;
 pub mod tile;
 
+use iced::keyboard::Modifiers;
 use iced::window::{self, Id, Settings};
 /// The default window width
 pub const WINDOW_WIDTH: f32 = 500.;
@@ -23,6 +34,69 @@ pub enum Page {
     Main,
     ClipboardHistory,
     EmojiSearch,
+    ShellOutput,
+    /// The view on top of the back stack
+    View,
+}
+
+impl Page {
+    /// What the page is called in the breadcrumbs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Page::Main => "Main",
+            Page::ClipboardHistory => "Clipboard",
+            Page::EmojiSearch => "Emoji",
+            Page::ShellOutput => "Output",
+            Page::View => "View",
+        }
+    }
+}
+
+/// A setting that can be turned on and off from the home screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Toggle {
+    HapticFeedback,
+    TrayIcon,
+    ScrollBar,
+}
+
+impl Toggle {
+    pub const ALL: [Toggle; 3] = [Toggle::HapticFeedback, Toggle::TrayIcon, Toggle::ScrollBar];
+
+    /// Whether the setting is on in the config
+    pub fn is_on(&self, config: &Config) -> bool {
+        match self {
+            Toggle::HapticFeedback => config.haptic_feedback,
+            Toggle::TrayIcon => config.show_trayicon,
+            Toggle::ScrollBar => config.theme.show_scroll_bar,
+        }
+    }
+
+    /// Turn the setting on if it's off, or off if it's on
+    pub fn flip(&self, config: &mut Config) {
+        let setting = match self {
+            Toggle::HapticFeedback => &mut config.haptic_feedback,
+            Toggle::TrayIcon => &mut config.show_trayicon,
+            Toggle::ScrollBar => &mut config.theme.show_scroll_bar,
+        };
+        *setting = !*setting;
+    }
+
+    /// A row that flips the setting, with whether it's on as its description
+    pub fn to_app(self, config: &Config) -> App {
+        let name = match self {
+            Toggle::HapticFeedback => "Haptic Feedback",
+            Toggle::TrayIcon => "Tray Icon",
+            Toggle::ScrollBar => "Scroll Bar",
+        };
+        App {
+            open_command: AppCommand::Message(Message::Toggle(self)),
+            desc: if self.is_on(config) { "On" } else { "Off" }.to_string(),
+            icons: None,
+            name: name.to_string(),
+            name_lc: String::new(),
+        }
+    }
 }
 
 /// The types of arrow keys
@@ -63,6 +137,30 @@ pub enum Message {
     SwitchToPage(Page),
     ClipboardHistory(ClipBoardContentType),
     ChangeFocus(ArrowKey),
+    /// The rows of a provider, with the generation of the query and the name and priority of the
+    /// provider
+    ProviderResults(u64, String, u8, Vec<App>, Id),
+    ShellOutput(ShellEvent),
+    /// Rows that an extension pushed for a query, with the name and priority of the extension
+    ExtensionUpdate(String, String, u8, Vec<App>),
+    /// Show a view on top of the current one
+    PushView(View),
+    /// A change to an input of the form that is shown, by the index of the input
+    FormInput(usize, InputValue),
+    SubmitForm,
+    /// Go back to the page before the current one
+    GoBack(Id),
+    /// Show the action panel of the focused row
+    ShowActions,
+    /// Show the apps that can open the file (or app) at this path
+    ShowOpenWith(String),
+    /// Run the script at this path, asking for the arguments that weren't typed after its alias
+    PromptArguments(String),
+    ModifiersChanged(Modifiers),
+    /// Turn a setting on or off, and save it to the config
+    Toggle(Toggle),
+    /// Pin or unpin the app with this name, and save it to the config
+    TogglePin(String),
 }
 
 /// The window settings for rustcast
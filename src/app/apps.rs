@@ -10,13 +10,38 @@ use iced::{
 };
 
 use crate::{
-    app::{Message, Page, RUSTCAST_DESC_NAME},
+    app::{
+        Message, Page, RUSTCAST_DESC_NAME,
+        pages::generic::{Field, Input, Metadata, Section, View},
+    },
     clipboard::ClipBoardContentType,
     commands::Function,
     styles::{result_button_style, result_row_container_style},
-    utils::handle_from_icns,
+    utils::{handle_from_icns, private_browser},
 };
 
+/// The details of RustCast, which the version row shows
+fn about_view(app_version: &str) -> View {
+    View::Detail {
+        title: "About RustCast".to_string(),
+        markdown: "# RustCast\n\
+            An open-source, Rust-powered launcher for apps, utilities, and workflows.\n\n\
+            - [Features](https://github.com/unsecretised/rustcast/blob/main/FEATURES.md)\n\
+            - [Issues](https://github.com/unsecretised/rustcast/issues)"
+            .to_string(),
+        metadata: vec![
+            Metadata {
+                label: "Version".to_string(),
+                value: app_version.to_string(),
+            },
+            Metadata {
+                label: "Config".to_string(),
+                value: "~/.config/rustcast/config.toml".to_string(),
+            },
+        ],
+    }
+}
+
 /// This tells each "App" what to do when it is clicked, whether it is a function, a message, or a display
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -26,6 +51,136 @@ pub enum AppCommand {
     Display,
 }
 
+/// Something that can be done with a result, which is shown in its action panel
+#[derive(Debug, Clone)]
+pub struct Action {
+    pub name: String,
+    pub command: AppCommand,
+}
+
+impl Action {
+    pub fn new(name: &str, command: AppCommand) -> Action {
+        Action {
+            name: name.to_string(),
+            command,
+        }
+    }
+}
+
+/// The apps that can open a file, which open it with that app instead of its default one
+pub fn open_with_view(path: &str, apps: Vec<App>) -> View {
+    let mut items = apps
+        .into_iter()
+        .filter_map(|app| match app.open_command {
+            AppCommand::Function(Function::OpenApp(app_path)) => Some(App {
+                open_command: AppCommand::Function(Function::OpenWith(path.to_string(), app_path)),
+                ..app
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if items.is_empty() {
+        items.push(App {
+            open_command: AppCommand::Display,
+            desc: String::new(),
+            icons: None,
+            name: "No apps can open this".to_string(),
+            name_lc: String::new(),
+        });
+    }
+    View::List {
+        title: "Open With".to_string(),
+        sections: vec![Section {
+            title: Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            items,
+        }],
+    }
+}
+
+/// The start of the ids of the forms that give an app an alias, which end with its name
+pub const ALIAS_FORM: &str = "alias:";
+
+/// The keys that run the first few actions of a result
+pub const ACTION_SHORTCUTS: [&str; 3] = if cfg!(target_os = "macos") {
+    ["↵", "⌘ ↵", "⌥ ↵"]
+} else {
+    ["↵", "Ctrl ↵", "Alt ↵"]
+};
+
+impl AppCommand {
+    /// The message that the command sends, if it isn't only displayed
+    pub fn message(&self) -> Option<Message> {
+        match self.clone() {
+            AppCommand::Function(func) => Some(Message::RunFunction(func)),
+            AppCommand::Message(msg) => Some(msg),
+            AppCommand::Display => None,
+        }
+    }
+
+    /// What the command is called in the action panel
+    fn name(&self) -> &'static str {
+        match self {
+            AppCommand::Function(
+                Function::CopyToClipboard(_) | Function::Calculate(_) | Function::RandomVar(_),
+            ) => "Copy",
+            AppCommand::Function(Function::RunShellCommand(_) | Function::RunExtensionItem(..))
+            | AppCommand::Message(Message::PromptArguments(_)) => "Run",
+            AppCommand::Function(Function::WebSearch(..)) => "Search",
+            _ => "Open",
+        }
+    }
+
+    /// Everything that can be done with a result that has this command, starting with the
+    /// command itself
+    pub fn actions(&self) -> Vec<Action> {
+        let mut actions = vec![Action::new(self.name(), self.clone())];
+        match self {
+            AppCommand::Function(Function::OpenApp(path) | Function::OpenFile(path)) => {
+                actions.extend([
+                    Action::new(
+                        "Show in Finder",
+                        AppCommand::Function(Function::RevealInFinder(path.clone())),
+                    ),
+                    Action::new(
+                        "Copy Path",
+                        AppCommand::Function(Function::CopyToClipboard(
+                            ClipBoardContentType::Text(path.clone()),
+                        )),
+                    ),
+                    Action::new(
+                        "Open With…",
+                        AppCommand::Message(Message::ShowOpenWith(path.clone())),
+                    ),
+                ]);
+                if let AppCommand::Function(Function::OpenApp(path)) = self {
+                    actions.push(Action::new(
+                        "Quit",
+                        AppCommand::Function(Function::QuitApp(path.clone())),
+                    ));
+                }
+            }
+            AppCommand::Function(Function::OpenWebsite(url)) => {
+                actions.push(Action::new(
+                    "Copy URL",
+                    AppCommand::Function(Function::CopyToClipboard(ClipBoardContentType::Text(
+                        url.clone(),
+                    ))),
+                ));
+                if private_browser().is_some() {
+                    actions.push(Action::new(
+                        "Open in Private Window",
+                        AppCommand::Function(Function::OpenPrivateWindow(url.clone())),
+                    ));
+                }
+            }
+            _ => {}
+        }
+        actions
+    }
+}
+
 /// The main app struct, that represents an "App"
 ///
 /// This struct represents a command that rustcast can perform, providing the rustcast
@@ -116,7 +271,7 @@ impl App {
                 name_lc: "refresh".to_string(),
             },
             App {
-                open_command: AppCommand::Display,
+                open_command: AppCommand::Message(Message::PushView(about_view(app_version))),
                 desc: RUSTCAST_DESC_NAME.to_string(),
                 icons: handle_from_icns(Path::new(
                     "/Applications/Rustcast.app/Contents/Resources/icon.icns",
@@ -127,14 +282,63 @@ impl App {
         ]
     }
 
+    /// The message that opening the app sends, if it isn't only displayed
+    pub fn message(&self) -> Option<Message> {
+        self.open_command.message()
+    }
+
+    /// The action panel of the app, as a view with a row for each action
+    pub fn actions_view(&self, actions: Vec<Action>) -> View {
+        let items = actions
+            .into_iter()
+            .enumerate()
+            .map(|(index, action)| App {
+                open_command: action.command,
+                desc: ACTION_SHORTCUTS.get(index).unwrap_or(&"").to_string(),
+                icons: None,
+                name_lc: action.name.to_lowercase(),
+                name: action.name,
+            })
+            .collect();
+        View::List {
+            title: "Actions".to_string(),
+            sections: vec![Section {
+                title: Some(self.name.clone()),
+                items,
+            }],
+        }
+    }
+
+    /// A form that gives the app an alias, which it can then be searched for by
+    pub fn alias_view(&self, alias: Option<&str>) -> View {
+        View::Form {
+            title: format!("Alias for {}", self.name),
+            id: format!("{ALIAS_FORM}{}", self.name),
+            fields: vec![Field {
+                id: "alias".to_string(),
+                label: "Alias (leave empty to remove it)".to_string(),
+                input: Input::Text {
+                    placeholder: self.name_lc.chars().take(2).collect(),
+                    value: alias.unwrap_or_default().to_string(),
+                },
+            }],
+            extension: None,
+        }
+    }
+
     /// This renders the app into an iced element, allowing it to be displayed in the search results
+    ///
+    /// The focused row shows how to open its action panel if `has_actions` is set, which is
+    /// whether the panel would have more than one action
     pub fn render(
         self,
         theme: crate::config::Theme,
         id_num: u32,
         focussed_id: u32,
+        has_actions: bool,
     ) -> iced::Element<'static, Message> {
         let focused = focussed_id == id_num;
+        let msg = self.message();
 
         // Title + subtitle (Raycast style)
         let text_block = iced::widget::Column::new()
@@ -170,11 +374,19 @@ impl App {
         }
         row = row.push(container(text_block).width(Fill));
 
-        let msg = match self.open_command.clone() {
-            AppCommand::Function(func) => Some(Message::RunFunction(func)),
-            AppCommand::Message(msg) => Some(msg),
-            AppCommand::Display => None,
-        };
+        if focused && has_actions {
+            let keys = if cfg!(target_os = "macos") {
+                "⌘ K"
+            } else {
+                "Ctrl K"
+            };
+            row = row.push(
+                Text::new(format!("Actions {keys}"))
+                    .font(theme.font())
+                    .size(12)
+                    .color(theme.text_color(0.45)),
+            );
+        }
 
         let theme_clone = theme.clone();
 
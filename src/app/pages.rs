@@ -1,5 +1,7 @@
 pub mod clipboard;
 pub mod common;
 pub mod emoji;
+pub mod generic;
 pub mod prelude;
 pub mod settings;
+pub mod shell_output;
@@ -17,7 +17,9 @@ pub fn clipboard_view(
         container(
             scrollable(
                 Column::from_iter(clipboard_content.iter().enumerate().map(|(i, content)| {
-                    content.to_app().render(theme.clone(), i as u32, focus_id)
+                    content
+                        .to_app()
+                        .render(theme.clone(), i as u32, focus_id, false)
                 }))
                 .width(WINDOW_WIDTH / 3.),
             )
@@ -0,0 +1,434 @@
+//! The generic page, which shows a view that an extension or a builtin feature returned
+//!
+//! Views are declarative, so that they can be sent by extensions as JSON: a list of rows in
+//! sections, a grid, a detail with markdown, or a form with inputs. Views are pushed onto a stack
+//! by [`Message::PushView`], and escape goes back to the one before.
+use iced::{
+    Border,
+    border::Radius,
+    widget::{checkbox, markdown, pick_list, scrollable, text_input},
+};
+use serde::Deserialize;
+
+use crate::{app::pages::prelude::*, commands::Function, styles::rustcast_text_input_style};
+
+/// How many cells are in a row of a grid, if the view doesn't say
+const DEFAULT_COLUMNS: usize = 4;
+
+/// A view, with rows of type `R`
+///
+/// Views are shown with [`App`] rows, and extensions send them with their own items, which are
+/// turned into apps with [`View::map_rows`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum View<R = App> {
+    List {
+        title: String,
+        sections: Vec<Section<R>>,
+    },
+    Grid {
+        title: String,
+        columns: Option<usize>,
+        items: Vec<R>,
+    },
+    Detail {
+        title: String,
+        markdown: String,
+        #[serde(default)]
+        metadata: Vec<Metadata>,
+    },
+    Form {
+        title: String,
+        id: String,
+        fields: Vec<Field>,
+        /// The extension that the form is sent to
+        #[serde(skip)]
+        extension: Option<String>,
+    },
+}
+
+/// Rows of a list, under an optional heading
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Section<R = App> {
+    pub title: Option<String>,
+    pub items: Vec<R>,
+}
+
+/// A label and its value, shown under the markdown of a detail
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Metadata {
+    pub label: String,
+    pub value: String,
+}
+
+/// An input of a form, with its current value
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Field {
+    pub id: String,
+    pub label: String,
+    #[serde(flatten)]
+    pub input: Input,
+}
+
+/// The kinds of inputs that forms can have
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "input", rename_all = "lowercase")]
+pub enum Input {
+    Text {
+        #[serde(default)]
+        placeholder: String,
+        #[serde(default)]
+        value: String,
+    },
+    Checkbox {
+        #[serde(default)]
+        value: bool,
+    },
+    Dropdown {
+        options: Vec<String>,
+        value: Option<String>,
+    },
+}
+
+/// A change to an input of a form
+#[derive(Debug, Clone)]
+pub enum InputValue {
+    Text(String),
+    Checked(bool),
+}
+
+impl<R> View<R> {
+    pub fn title(&self) -> &str {
+        match self {
+            View::List { title, .. }
+            | View::Grid { title, .. }
+            | View::Detail { title, .. }
+            | View::Form { title, .. } => title,
+        }
+    }
+
+    /// Turn the rows of the view into another type, like the items of an extension into apps
+    pub fn map_rows<S>(self, mut map: impl FnMut(R) -> S) -> View<S> {
+        match self {
+            View::List { title, sections } => View::List {
+                title,
+                sections: sections
+                    .into_iter()
+                    .map(|section| Section {
+                        title: section.title,
+                        items: section.items.into_iter().map(&mut map).collect(),
+                    })
+                    .collect(),
+            },
+            View::Grid {
+                title,
+                columns,
+                items,
+            } => View::Grid {
+                title,
+                columns,
+                items: items.into_iter().map(map).collect(),
+            },
+            View::Detail {
+                title,
+                markdown,
+                metadata,
+            } => View::Detail {
+                title,
+                markdown,
+                metadata,
+            },
+            View::Form {
+                title,
+                id,
+                fields,
+                extension,
+            } => View::Form {
+                title,
+                id,
+                fields,
+                extension,
+            },
+        }
+    }
+}
+
+/// A view on the stack, with its markdown parsed once instead of on every frame
+#[derive(Debug, Clone)]
+pub struct ViewState {
+    pub view: View,
+    markdown: Vec<markdown::Item>,
+}
+
+impl ViewState {
+    pub fn new(view: View) -> ViewState {
+        let markdown = match &view {
+            View::Detail { markdown, .. } => markdown::parse(markdown).collect(),
+            _ => vec![],
+        };
+        ViewState { view, markdown }
+    }
+
+    /// The rows of the view that match the query, in the order that they are shown
+    pub fn rows(&self, query_lc: &str) -> Vec<&App> {
+        match &self.view {
+            View::List { sections, .. } => sections
+                .iter()
+                .flat_map(|section| section.items.iter().filter(|app| matches(app, query_lc)))
+                .collect(),
+            View::Grid { items, .. } => items.iter().filter(|app| matches(app, query_lc)).collect(),
+            View::Detail { .. } | View::Form { .. } => vec![],
+        }
+    }
+
+    /// How many rows the arrow keys move by, which is a whole row of a grid for up and down
+    pub fn columns(&self) -> usize {
+        match &self.view {
+            View::Grid { columns, .. } => columns.unwrap_or(DEFAULT_COLUMNS).max(1),
+            _ => 1,
+        }
+    }
+
+    /// Change an input of a form
+    pub fn set_input(&mut self, index: usize, value: InputValue) {
+        let View::Form { fields, .. } = &mut self.view else {
+            return;
+        };
+        let Some(field) = fields.get_mut(index) else {
+            return;
+        };
+        match (&mut field.input, value) {
+            (Input::Text { value, .. }, InputValue::Text(text)) => *value = text,
+            (Input::Checkbox { value }, InputValue::Checked(checked)) => *value = checked,
+            (Input::Dropdown { value, .. }, InputValue::Text(text)) => *value = Some(text),
+            _ => {}
+        }
+    }
+
+    /// The values of a form, by the ids of its fields
+    pub fn form_values(&self) -> serde_json::Map<String, serde_json::Value> {
+        let View::Form { fields, .. } = &self.view else {
+            return serde_json::Map::new();
+        };
+        fields
+            .iter()
+            .map(|field| {
+                let value = match &field.input {
+                    Input::Text { value, .. } => value.clone().into(),
+                    Input::Checkbox { value } => (*value).into(),
+                    Input::Dropdown { value, .. } => value.clone().into(),
+                };
+                (field.id.clone(), value)
+            })
+            .collect()
+    }
+}
+
+/// Whether a row of a list or grid matches the query
+fn matches(app: &App, query_lc: &str) -> bool {
+    query_lc.is_empty()
+        || app.name.to_lowercase().contains(query_lc)
+        || app.desc.to_lowercase().contains(query_lc)
+}
+
+/// Show a view, with the rows that match the query
+///
+/// `has_actions` is whether the focused row has an action panel
+pub fn generic_view<'a>(
+    state: &'a ViewState,
+    query_lc: &str,
+    theme: Theme,
+    focus_id: u32,
+    has_actions: bool,
+) -> Element<'a, Message> {
+    let theme_clone = theme.clone();
+    let content: Element<'a, Message> = match &state.view {
+        View::List { sections, .. } => {
+            list(sections, query_lc, theme.clone(), focus_id, has_actions)
+        }
+        View::Grid { .. } => grid(
+            state.rows(query_lc),
+            state.columns(),
+            theme.clone(),
+            focus_id,
+        ),
+        View::Detail { metadata, .. } => {
+            let mut column = Column::new().spacing(10).push(
+                markdown::view(&state.markdown, markdown_settings(&theme))
+                    .map(|url| Message::RunFunction(Function::OpenWebsite(url))),
+            );
+            for item in metadata {
+                column = column.push(
+                    Row::new()
+                        .spacing(10)
+                        .push(
+                            Text::new(item.label.clone())
+                                .font(theme.font())
+                                .size(13)
+                                .color(theme.text_color(0.55))
+                                .width(WINDOW_WIDTH / 3.),
+                        )
+                        .push(
+                            Text::new(item.value.clone())
+                                .font(theme.font())
+                                .size(13)
+                                .color(theme.text_color(0.9)),
+                        ),
+                );
+            }
+            container(column).padding(10).into()
+        }
+        View::Form { fields, .. } => form(fields, theme.clone()),
+    };
+
+    container(scrollable(content).id("results"))
+        .height(385)
+        .width(WINDOW_WIDTH)
+        .style(move |_| result_row_container_style(&theme_clone, false))
+        .into()
+}
+
+/// The rows of a list that match the query, under the titles of their sections
+pub fn list(
+    sections: &[Section],
+    query_lc: &str,
+    theme: Theme,
+    focus_id: u32,
+    has_actions: bool,
+) -> Element<'static, Message> {
+    let mut column = Column::new();
+    let mut index = 0;
+    for section in sections {
+        let rows = section
+            .items
+            .iter()
+            .filter(|app| matches(app, query_lc))
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            continue;
+        }
+        if let Some(title) = &section.title {
+            column = column.push(
+                container(
+                    Text::new(title.clone())
+                        .font(theme.font())
+                        .size(12)
+                        .color(theme.text_color(0.55)),
+                )
+                .padding([4, 12]),
+            );
+        }
+        for app in rows {
+            column = column.push(
+                app.clone()
+                    .render(theme.clone(), index, focus_id, has_actions),
+            );
+            index += 1;
+        }
+    }
+    column.into()
+}
+
+/// The text size and colours of the markdown in a detail
+fn markdown_settings(theme: &Theme) -> markdown::Settings {
+    let mut style = markdown::Style::from(iced::Theme::from(theme.clone()));
+    style.font = theme.font();
+    markdown::Settings::with_text_size(14, style)
+}
+
+/// The cells of a grid, in rows of the given number of columns
+fn grid(apps: Vec<&App>, columns: usize, theme: Theme, focus_id: u32) -> Element<'static, Message> {
+    let size = WINDOW_WIDTH / columns as f32 - 12.;
+    let mut column = Column::new().spacing(8).padding(5);
+    for (row_index, row_apps) in apps.chunks(columns).enumerate() {
+        let mut row = Row::new().spacing(8);
+        for (column_index, app) in row_apps.iter().enumerate() {
+            let index = (row_index * columns + column_index) as u32;
+            let mut cell = Column::new().align_x(Alignment::Center).spacing(4);
+            if let Some(icon) = &app.icons {
+                cell = cell.push(iced::widget::image::Viewer::new(icon).width(40).height(40));
+            }
+            cell = cell.push(
+                Text::new(app.name.clone())
+                    .font(theme.font())
+                    .size(13)
+                    .color(theme.text_color(1.0))
+                    .align_x(Alignment::Center)
+                    .width(Length::Fill),
+            );
+
+            let button_theme = theme.clone();
+            let container_theme = theme.clone();
+            row = row.push(
+                container(
+                    Button::new(cell)
+                        .width(size)
+                        .height(size)
+                        .on_press_maybe(app.message())
+                        .style(move |_, _| emoji_button_style(&button_theme)),
+                )
+                .id(format!("result-{index}"))
+                .style(move |_| emoji_button_container_style(&container_theme, focus_id == index)),
+            );
+        }
+        column = column.push(row);
+    }
+    column.into()
+}
+
+/// The inputs of a form, and the button that sends it
+fn form(fields: &[Field], theme: Theme) -> Element<'static, Message> {
+    let mut column = Column::new().spacing(12).padding(10);
+    for (index, field) in fields.iter().enumerate() {
+        let label = Text::new(field.label.clone())
+            .font(theme.font())
+            .size(13)
+            .color(theme.text_color(0.7));
+        let input: Element<'static, Message> = match &field.input {
+            Input::Text { placeholder, value } => {
+                let input_theme = theme.clone();
+                text_input(placeholder, value)
+                    .on_input(move |text| Message::FormInput(index, InputValue::Text(text)))
+                    .on_submit(Message::SubmitForm)
+                    .font(theme.font())
+                    .padding(8)
+                    .style(move |_, _| rustcast_text_input_style(&input_theme, false))
+                    .into()
+            }
+            Input::Checkbox { value } => checkbox(*value)
+                .on_toggle(move |checked| Message::FormInput(index, InputValue::Checked(checked)))
+                .into(),
+            Input::Dropdown { options, value } => {
+                pick_list(options.clone(), value.clone(), move |option| {
+                    Message::FormInput(index, InputValue::Text(option))
+                })
+                .font(theme.font())
+                .into()
+            }
+        };
+        column = column.push(Column::new().spacing(4).push(label).push(input));
+    }
+
+    let button_theme = theme.clone();
+    column
+        .push(
+            Button::new(
+                Text::new("Submit")
+                    .font(theme.font())
+                    .size(14)
+                    .color(theme.text_color(1.0)),
+            )
+            .padding([6, 16])
+            .on_press(Message::SubmitForm)
+            .style(move |_, _| iced::widget::button::Style {
+                background: Some(Background::Color(tint(button_theme.bg_color(), 0.1))),
+                border: Border {
+                    color: button_theme.text_color(0.2),
+                    width: 1.,
+                    radius: Radius::new(6),
+                },
+                ..Default::default()
+            }),
+        )
+        .into()
+}
@@ -0,0 +1,105 @@
+//! The page that shows the output of a shell command as it runs
+use std::time::{Duration, Instant};
+
+use iced::{task, widget::scrollable};
+
+use crate::{
+    app::pages::prelude::*,
+    shell::{ShellEvent, describe_exit},
+};
+
+/// The most lines that are kept, so that a command that prints forever doesn't use up the memory
+const MAX_LINES: usize = 5000;
+
+/// Whether the command is still running, and how it ended if it isn't
+#[derive(Debug, Clone)]
+pub enum RunState {
+    Running(task::Handle),
+    Exited(Option<i32>, Duration),
+    TimedOut(Duration),
+    Cancelled(Duration),
+}
+
+/// A shell command whose output is shown inline
+#[derive(Debug, Clone)]
+pub struct ShellRun {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub started: Instant,
+    pub state: RunState,
+}
+
+impl ShellRun {
+    pub fn new(title: String, handle: task::Handle) -> ShellRun {
+        ShellRun {
+            title,
+            lines: vec![],
+            started: Instant::now(),
+            state: RunState::Running(handle),
+        }
+    }
+
+    /// Add what the command sent back
+    pub fn handle_event(&mut self, event: ShellEvent) {
+        let elapsed = self.started.elapsed();
+        match event {
+            ShellEvent::Line(line) => {
+                if self.lines.len() == MAX_LINES {
+                    self.lines.remove(0);
+                }
+                self.lines.push(line);
+            }
+            ShellEvent::Finished(code) => self.state = RunState::Exited(code, elapsed),
+            ShellEvent::TimedOut => self.state = RunState::TimedOut(elapsed),
+        }
+    }
+
+    /// Stop the command if it is still running
+    ///
+    /// Returns false if it had already finished
+    pub fn cancel(&mut self) -> bool {
+        let RunState::Running(handle) = &self.state else {
+            return false;
+        };
+        handle.abort();
+        self.state = RunState::Cancelled(self.started.elapsed());
+        true
+    }
+
+    /// The status of the command, for the footer
+    pub fn status(&self) -> String {
+        let elapsed = |duration: &Duration| format!("{:.1}s", duration.as_secs_f64());
+        match &self.state {
+            RunState::Running(_) => format!("{}: running, esc to cancel", self.title),
+            RunState::Exited(code, duration) => {
+                format!("{}: {}", self.title, describe_exit(*code, *duration))
+            }
+            RunState::TimedOut(duration) => {
+                format!("{}: timed out after {}", self.title, elapsed(duration))
+            }
+            RunState::Cancelled(duration) => {
+                format!("{}: cancelled after {}", self.title, elapsed(duration))
+            }
+        }
+    }
+}
+
+pub fn shell_output_view(run: &ShellRun, theme: Theme) -> Element<'static, Message> {
+    let theme_clone = theme.clone();
+    container(
+        scrollable(
+            Text::new(run.lines.join("\n"))
+                .font(theme.font())
+                .size(13)
+                .color(theme.text_color(0.9))
+                .width(Length::Fill),
+        )
+        .anchor_bottom()
+        .id("results"),
+    )
+    .padding(10)
+    .height(385)
+    .width(WINDOW_WIDTH)
+    .style(move |_| result_row_container_style(&theme_clone, false))
+    .into()
+}
@@ -0,0 +1,158 @@
+//! Query providers, which each turn the search query into rows of results
+//!
+//! Every provider that matches a query adds its rows to the results, next to the apps that were
+//! found, and the rows are ranked by the priority of the provider that made them. Each provider
+//! runs as its own task (see [`crate::app::tile::Tile::start_search`]), so a slow one doesn't hold
+//! up the rest. A new provider only has to implement [`QueryProvider`] and be added to
+//! [`ProviderRegistry::from_config`].
+pub mod aliases;
+pub mod bookmarks;
+pub mod calculator;
+pub mod easter_eggs;
+pub mod extensions;
+pub mod files;
+pub mod recent;
+pub mod time;
+pub mod web;
+
+use std::sync::Arc;
+
+use crate::{
+    app::apps::{App, AppCommand},
+    clipboard::ClipBoardContentType,
+    commands::Function,
+    config::Config,
+    extensions::{load_extensions, wasm},
+    scripts,
+};
+
+/// The priority that rows from the app index are ranked at, so that providers can be put above or
+/// below the apps
+pub const APPS_PRIORITY: u8 = 50;
+
+/// The priority of the pinned apps, which are ranked above the rows of every provider
+pub const PINNED_PRIORITY: u8 = u8::MAX;
+
+/// The query that providers are given, which owns its data so that it can be sent to other
+/// threads along with the provider
+#[derive(Debug, Clone)]
+pub struct Query {
+    /// The query as it was typed
+    pub raw: String,
+    /// The query trimmed and in lowercase
+    pub lower: String,
+}
+
+impl Query {
+    pub fn new(raw: &str) -> Query {
+        Query {
+            raw: raw.to_string(),
+            lower: raw.trim().to_lowercase(),
+        }
+    }
+}
+
+/// Something that can turn a query into result rows, like the calculator or the web search
+///
+/// Providers are `Send + Sync` and only get an owned [`Query`], so that they can be run off the
+/// main thread
+pub trait QueryProvider: Send + Sync {
+    /// The name of the provider, for logs and errors
+    fn name(&self) -> &str;
+
+    /// Where the rows of this provider are ranked, with higher priorities first (the apps are at
+    /// [`APPS_PRIORITY`])
+    fn priority(&self) -> u8;
+
+    /// A quick check of whether the provider has anything to say about the query
+    fn matches(&self, query: &Query) -> bool;
+
+    /// The rows for the query, which is only called if [`QueryProvider::matches`] returned true
+    fn produce(&self, query: &Query) -> Vec<App>;
+}
+
+/// All the providers that queries are run through
+#[derive(Default)]
+pub struct ProviderRegistry {
+    providers: Vec<Arc<dyn QueryProvider>>,
+}
+
+impl ProviderRegistry {
+    /// The builtin providers, set up from the config
+    pub fn from_config(config: &Config) -> ProviderRegistry {
+        let mut registry = ProviderRegistry::default();
+        registry.register(easter_eggs::EasterEggProvider);
+        registry.register(time::DateProvider);
+        registry.register(time::WorldClockProvider::new(config.world_clock.clone()));
+        registry.register(calculator::CalculatorProvider);
+        registry.register(calculator::UnitConversionProvider);
+        registry.register(web::UrlProvider::new(config.intranet_hosts.clone()));
+        registry.register(aliases::AliasProvider::new(
+            config
+                .quicklinks
+                .iter()
+                .map(|link| link.to_app())
+                .chain(config.shells.iter().map(|shell| shell.to_app()))
+                .chain(scripts::load_scripts().iter().map(|script| script.to_app()))
+                .collect(),
+        ));
+        registry.register(web::SearchEngineProvider::new(&config.search_engines));
+        registry.register(web::WebSearchProvider::new(config));
+        registry.register(bookmarks::BookmarkProvider::new(
+            &config.browser_profiles,
+            config.browser_history,
+        ));
+        registry.register(files::FileProvider::new(&config.file_search));
+        if config.recent_files.enabled {
+            registry.register(recent::RecentProvider::new(
+                &config.recent_files,
+                &config.file_search.prefix,
+            ));
+        }
+        for extension in load_extensions(&config.extensions) {
+            registry.register(extensions::ExtensionProvider::new(extension));
+        }
+        for extension in wasm::load_extensions() {
+            registry.register(extensions::WasmExtensionProvider::new(extension));
+        }
+        registry
+    }
+
+    /// Add a provider to the registry
+    pub fn register(&mut self, provider: impl QueryProvider + 'static) {
+        self.providers.push(Arc::new(provider));
+    }
+
+    /// The providers that have something to say about the query
+    pub fn matching(&self, query: &Query) -> Vec<Arc<dyn QueryProvider>> {
+        self.providers
+            .iter()
+            .filter(|provider| provider.matches(query))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A row that copies its value when it's opened
+pub fn copy_row(value: String, desc: String) -> App {
+    App {
+        open_command: AppCommand::Function(Function::CopyToClipboard(ClipBoardContentType::Text(
+            value.clone(),
+        ))),
+        desc,
+        icons: None,
+        name: value,
+        name_lc: String::new(),
+    }
+}
+
+/// A row that shows an error from a provider
+pub fn error_row(error: String, desc: &str) -> App {
+    App {
+        open_command: AppCommand::Display,
+        desc: desc.to_string(),
+        icons: None,
+        name: error,
+        name_lc: String::new(),
+    }
+}
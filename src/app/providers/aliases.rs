@@ -0,0 +1,58 @@
+//! The provider for commands that take the text after their alias, like quicklinks and shell
+//! commands
+use crate::app::{
+    apps::App,
+    providers::{Query, QueryProvider},
+};
+
+/// Shows a quicklink or shell command with the text after its alias filled in, like
+/// `jira PROJ-123` or `mkcd projects`
+///
+/// Without any text after the alias, they are found through the app index like any other app
+pub struct AliasProvider {
+    apps: Vec<App>,
+}
+
+impl AliasProvider {
+    /// The apps, with their `name_lc` as the alias
+    pub fn new(apps: Vec<App>) -> AliasProvider {
+        AliasProvider { apps }
+    }
+
+    /// The app whose alias the query starts with, and the text after the alias
+    fn split<'a>(&self, query: &'a Query) -> Option<(&App, &'a str)> {
+        let raw = query.raw.trim();
+        self.apps.iter().find_map(|app| {
+            let alias = &app.name_lc;
+            raw.get(..alias.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(alias))?;
+            let args = raw.get(alias.len()..)?.strip_prefix(' ')?.trim();
+            (!args.is_empty()).then_some((app, args))
+        })
+    }
+}
+
+impl QueryProvider for AliasProvider {
+    fn name(&self) -> &'static str {
+        "Aliases"
+    }
+
+    fn priority(&self) -> u8 {
+        70
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        self.split(query).is_some()
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        self.split(query)
+            .map(|(app, args)| {
+                vec![App {
+                    name: format!("{}: {}", app.name, args),
+                    ..app.clone()
+                }]
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,66 @@
+//! The browser bookmarks and history provider
+use std::path::PathBuf;
+
+use crate::{
+    app::{
+        apps::{App, AppCommand},
+        providers::{Query, QueryProvider},
+    },
+    bookmarks::{self, Source},
+    commands::Function,
+};
+
+/// How many bookmarks are shown at once
+const MAX_RESULTS: usize = 5;
+
+/// Searches the bookmarks (and history) of the browser profiles in the config
+pub struct BookmarkProvider {
+    enabled: bool,
+}
+
+impl BookmarkProvider {
+    /// Start keeping the bookmarks of the profiles up to date, if they aren't already
+    pub fn new(profiles: &[String], history: bool) -> BookmarkProvider {
+        let home = std::env::var("HOME").unwrap_or_default();
+        bookmarks::watch(
+            profiles
+                .iter()
+                .map(|profile| PathBuf::from(profile.replace("~", &home)))
+                .collect(),
+            history,
+        );
+        BookmarkProvider {
+            enabled: !profiles.is_empty(),
+        }
+    }
+}
+
+impl QueryProvider for BookmarkProvider {
+    fn name(&self) -> &'static str {
+        "Bookmarks"
+    }
+
+    fn priority(&self) -> u8 {
+        45
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        self.enabled && query.lower.chars().count() >= 2
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        bookmarks::search(&query.lower, MAX_RESULTS)
+            .into_iter()
+            .map(|bookmark| App {
+                open_command: AppCommand::Function(Function::OpenWebsite(bookmark.url)),
+                desc: match bookmark.source {
+                    Source::Bookmark => "Bookmark".to_string(),
+                    Source::History => "History".to_string(),
+                },
+                icons: None,
+                name: bookmark.title,
+                name_lc: String::new(),
+            })
+            .collect()
+    }
+}
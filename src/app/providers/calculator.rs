@@ -0,0 +1,76 @@
+//! The calculator and unit conversion providers
+use crate::{
+    app::{
+        RUSTCAST_DESC_NAME,
+        apps::{App, AppCommand},
+        providers::{Query, QueryProvider, copy_row, error_row},
+    },
+    calculator::Expr,
+    commands::Function,
+    date_calculator, unit_conversion,
+};
+
+/// Whether the query is about dates, which the date provider answers, so that dates like
+/// `2026-03-01` aren't also read as subtractions
+fn is_date(query: &Query) -> bool {
+    date_calculator::date_query(&query.raw).is_some()
+}
+
+/// Evaluates maths, like `2^(1+2)` or `log(100)`
+pub struct CalculatorProvider;
+
+impl QueryProvider for CalculatorProvider {
+    fn name(&self) -> &'static str {
+        "Calculator"
+    }
+
+    fn priority(&self) -> u8 {
+        80
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        // Words can be parsed as units (`m` or `in`), so they're only maths with a digit in them
+        query.lower.chars().any(|c| c.is_ascii_digit()) && !is_date(query)
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        match Expr::from_str(&query.raw) {
+            Ok(res) if !res.has_units() => vec![App {
+                open_command: AppCommand::Function(Function::Calculate(res.clone())),
+                desc: RUSTCAST_DESC_NAME.to_string(),
+                icons: None,
+                name: res.eval().map(|x| x.to_string()).unwrap_or("".to_string()),
+                name_lc: "".to_string(),
+            }],
+            _ => vec![],
+        }
+    }
+}
+
+/// Converts between units, like `5 ft to cm` or `$20 to eur`
+pub struct UnitConversionProvider;
+
+impl QueryProvider for UnitConversionProvider {
+    fn name(&self) -> &'static str {
+        "Unit Conversion"
+    }
+
+    fn priority(&self) -> u8 {
+        80
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        query.lower.chars().any(|c| c.is_ascii_digit()) && !is_date(query)
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        match unit_conversion::convert_query(&query.raw) {
+            Some(Ok(conversions)) => conversions
+                .into_iter()
+                .map(|conversion| copy_row(conversion.target.to_string(), conversion.description()))
+                .collect(),
+            Some(Err(error)) => vec![error_row(error, self.name())],
+            None => vec![],
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! The easter eggs (_randomvar_, _67_ and _lemon_)
+use std::path::Path;
+
+use iced::widget::image::Handle;
+
+use crate::{
+    app::{
+        apps::{App, AppCommand},
+        providers::{Query, QueryProvider},
+    },
+    commands::Function,
+};
+
+pub struct EasterEggProvider;
+
+impl QueryProvider for EasterEggProvider {
+    fn name(&self) -> &'static str {
+        "Easter egg"
+    }
+
+    fn priority(&self) -> u8 {
+        100
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        matches!(query.lower.as_str(), "randomvar" | "67" | "lemon")
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        let row = match query.lower.as_str() {
+            "randomvar" => {
+                let rand_num = rand::random_range(0..100);
+                App {
+                    open_command: AppCommand::Function(Function::RandomVar(rand_num)),
+                    desc: self.name().to_string(),
+                    icons: None,
+                    name: rand_num.to_string(),
+                    name_lc: String::new(),
+                }
+            }
+            "67" => App {
+                open_command: AppCommand::Function(Function::RandomVar(67)),
+                desc: self.name().to_string(),
+                icons: None,
+                name: 67.to_string(),
+                name_lc: String::new(),
+            },
+            _ => App {
+                open_command: AppCommand::Display,
+                desc: self.name().to_string(),
+                icons: Some(Handle::from_path(Path::new(
+                    "/Applications/Rustcast.app/Contents/Resources/lemon.png",
+                ))),
+                name: "Lemon".to_string(),
+                name_lc: "".to_string(),
+            },
+        };
+        vec![row]
+    }
+}
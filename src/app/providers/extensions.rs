@@ -0,0 +1,103 @@
+//! The providers for extensions, which send the query to an extension's process or component
+use std::sync::Arc;
+
+use crate::{
+    app::{
+        apps::App,
+        providers::{Query, QueryProvider},
+    },
+    extensions::{Extension, wasm::WasmExtension},
+    utils::log_error,
+};
+
+/// Searches one of the extensions from the config
+pub struct ExtensionProvider {
+    keyword: Option<String>,
+    extension: Arc<Extension>,
+}
+
+impl ExtensionProvider {
+    pub fn new(extension: Arc<Extension>) -> ExtensionProvider {
+        ExtensionProvider {
+            keyword: extension
+                .config
+                .keyword
+                .as_ref()
+                .map(|keyword| keyword.to_lowercase()),
+            extension,
+        }
+    }
+}
+
+impl QueryProvider for ExtensionProvider {
+    fn name(&self) -> &str {
+        &self.extension.config.name
+    }
+
+    fn priority(&self) -> u8 {
+        self.extension.priority()
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        matches_keyword(self.keyword.as_deref(), query)
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        self.extension.search(&query.raw).unwrap_or_else(|error| {
+            log_error(&format!("{error}\n"));
+            vec![]
+        })
+    }
+}
+
+/// Searches one of the WASM extensions
+pub struct WasmExtensionProvider {
+    keyword: Option<String>,
+    extension: Arc<WasmExtension>,
+}
+
+impl WasmExtensionProvider {
+    pub fn new(extension: Arc<WasmExtension>) -> WasmExtensionProvider {
+        WasmExtensionProvider {
+            keyword: extension
+                .manifest
+                .keyword
+                .as_ref()
+                .map(|keyword| keyword.to_lowercase()),
+            extension,
+        }
+    }
+}
+
+impl QueryProvider for WasmExtensionProvider {
+    fn name(&self) -> &str {
+        &self.extension.manifest.name
+    }
+
+    fn priority(&self) -> u8 {
+        self.extension.priority()
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        matches_keyword(self.keyword.as_deref(), query)
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        self.extension.search(&query.raw).unwrap_or_else(|error| {
+            log_error(&format!("{error}\n"));
+            vec![]
+        })
+    }
+}
+
+/// Whether the query starts with the keyword of an extension, or isn't empty for an extension
+/// without one
+fn matches_keyword(keyword: Option<&str>, query: &Query) -> bool {
+    match keyword {
+        Some(keyword) => query
+            .lower
+            .strip_prefix(keyword)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' ')),
+        None => !query.lower.is_empty(),
+    }
+}
@@ -0,0 +1,89 @@
+//! The file search provider
+use std::path::Path;
+
+use crate::{
+    app::{
+        apps::{App, AppCommand},
+        providers::{Query, QueryProvider},
+    },
+    commands::Function,
+    config::FileSearch,
+    files::{self, FileKind},
+    recent,
+};
+
+/// How many files are shown at once
+const MAX_RESULTS: usize = 8;
+
+/// Searches the file index for queries that start with the prefix, like `f report`
+pub struct FileProvider {
+    prefix: String,
+}
+
+impl FileProvider {
+    /// Start keeping the index up to date with the settings, if it isn't already
+    pub fn new(settings: &FileSearch) -> FileProvider {
+        files::watch(settings);
+        FileProvider {
+            prefix: settings.prefix.to_lowercase(),
+        }
+    }
+
+    /// The text after the prefix
+    fn search_text<'a>(&self, query: &'a Query) -> Option<&'a str> {
+        query
+            .lower
+            .strip_prefix(&self.prefix)?
+            .strip_prefix(' ')
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+    }
+}
+
+impl QueryProvider for FileProvider {
+    fn name(&self) -> &'static str {
+        "Files"
+    }
+
+    fn priority(&self) -> u8 {
+        70
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        self.search_text(query).is_some()
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        let Some(text) = self.search_text(query) else {
+            return vec![];
+        };
+        // The recent files that match are shown by the recent files provider, above these
+        let recent = recent::search(text, super::recent::MAX_RESULTS)
+            .into_iter()
+            .map(|file| file.path)
+            .collect::<Vec<_>>();
+
+        files::search(text, MAX_RESULTS + recent.len())
+            .into_iter()
+            .filter(|entry| !recent.contains(&entry.path))
+            .take(MAX_RESULTS)
+            .map(|entry| file_row(&entry.path, entry.name, entry.kind))
+            .collect()
+    }
+}
+
+/// A row that opens a file, with the folder that it's in as its description
+pub fn file_row(path: &Path, name: String, kind: FileKind) -> App {
+    let home = std::env::var("HOME").unwrap_or_default();
+    let folder = path
+        .parent()
+        .map(|parent| parent.to_string_lossy().replacen(&home, "~", 1))
+        .unwrap_or_default();
+    App {
+        open_command: AppCommand::Function(Function::OpenFile(path.to_string_lossy().into_owned())),
+        desc: folder,
+        icons: files::icon(kind),
+        name,
+        name_lc: String::new(),
+    }
+}
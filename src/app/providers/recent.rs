@@ -0,0 +1,68 @@
+//! The recently used files provider
+use crate::{
+    app::{
+        apps::App,
+        providers::{Query, QueryProvider, files::file_row},
+    },
+    config::RecentFiles,
+    recent,
+};
+
+/// How many recent files are shown at once
+pub const MAX_RESULTS: usize = 8;
+
+/// Shows the recently used files for queries that start with the prefix, like `recent report`,
+/// and the ones that match a file search above the rest of the files
+pub struct RecentProvider {
+    prefix: String,
+    file_prefix: String,
+}
+
+impl RecentProvider {
+    /// Start keeping the list of recent files up to date, if it isn't already
+    pub fn new(settings: &RecentFiles, file_prefix: &str) -> RecentProvider {
+        recent::watch();
+        RecentProvider {
+            prefix: settings.prefix.to_lowercase(),
+            file_prefix: file_prefix.to_lowercase(),
+        }
+    }
+
+    /// The text after the prefix, which can be empty, or the text of a file search
+    fn search_text<'a>(&self, query: &'a Query) -> Option<&'a str> {
+        let after = |prefix: &str| {
+            query
+                .lower
+                .strip_prefix(prefix)
+                .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                .map(str::trim)
+        };
+        after(&self.prefix).or_else(|| after(&self.file_prefix).filter(|text| !text.is_empty()))
+    }
+}
+
+impl QueryProvider for RecentProvider {
+    fn name(&self) -> &'static str {
+        "Recent Files"
+    }
+
+    fn priority(&self) -> u8 {
+        71
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        self.search_text(query).is_some()
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        self.search_text(query).map(rows).unwrap_or_default()
+    }
+}
+
+/// The recent files that match the text, or the most recent ones if it's empty
+pub fn rows(text: &str) -> Vec<App> {
+    recent::search(text, MAX_RESULTS)
+        .into_iter()
+        .map(|file| file_row(&file.path, file.name, file.kind))
+        .collect()
+}
@@ -0,0 +1,77 @@
+//! The date calculator and world clock providers
+use crate::{
+    app::{
+        apps::App,
+        providers::{Query, QueryProvider, copy_row, error_row},
+    },
+    date_calculator, world_clock,
+};
+
+/// Date arithmetic and timestamps, like `today + 45 days` or `1700000000`
+pub struct DateProvider;
+
+impl QueryProvider for DateProvider {
+    fn name(&self) -> &'static str {
+        "Date Calculation"
+    }
+
+    fn priority(&self) -> u8 {
+        90
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        // Parsing is cheap, and is the only real way to tell whether a query is a date
+        !query.lower.is_empty()
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        match date_calculator::date_query(&query.raw) {
+            Some(Ok(dates)) => dates
+                .into_iter()
+                .map(|date| copy_row(date.value, date.description))
+                .collect(),
+            Some(Err(error)) => vec![error_row(error, self.name())],
+            None => vec![],
+        }
+    }
+}
+
+/// Times around the world, like `time in Tokyo` or `3pm PST in Berlin`
+pub struct WorldClockProvider {
+    favourites: Vec<String>,
+}
+
+impl WorldClockProvider {
+    /// A world clock that shows the `favourites` when searching for `time`
+    pub fn new(favourites: Vec<String>) -> WorldClockProvider {
+        WorldClockProvider { favourites }
+    }
+}
+
+impl QueryProvider for WorldClockProvider {
+    fn name(&self) -> &'static str {
+        "World Clock"
+    }
+
+    fn priority(&self) -> u8 {
+        90
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        query.lower == "time"
+            || query.lower == "now"
+            || query.lower.contains(" in ")
+            || query.lower.contains(" to ")
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        match world_clock::time_query(&query.raw, &self.favourites) {
+            Some(Ok(times)) => times
+                .into_iter()
+                .map(|time| copy_row(time.time_label(), time.description()))
+                .collect(),
+            Some(Err(error)) => vec![error_row(error, self.name())],
+            None => vec![],
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! The website and web search providers
+use iced::widget::image::Handle;
+
+use crate::{
+    app::{
+        apps::{App, AppCommand},
+        providers::{Query, QueryProvider},
+    },
+    commands::Function,
+    config::{Config, SearchEngine},
+    utils::{handle_from_path, parse_url},
+};
+
+/// A search engine from the config, with its icon loaded
+struct Engine {
+    keyword: String,
+    name: String,
+    url: String,
+    icon: Option<Handle>,
+}
+
+impl Engine {
+    fn from_config(engine: &SearchEngine) -> Engine {
+        Engine {
+            keyword: engine.keyword.to_lowercase(),
+            name: engine.name.clone(),
+            url: engine.url.clone(),
+            icon: engine.icon_path.as_deref().and_then(handle_from_path),
+        }
+    }
+
+    /// A row that searches this engine for the search arguments
+    fn row(&self, args: &str) -> App {
+        App {
+            open_command: AppCommand::Function(Function::WebSearch(
+                self.url.clone(),
+                args.to_string(),
+            )),
+            icons: self.icon.clone(),
+            desc: "Web Search".to_string(),
+            name: format!("Search {} for: {}", self.name, args),
+            name_lc: String::new(),
+        }
+    }
+}
+
+/// Opens queries that look like websites, like `github.com`, `localhost:8080` or `file:///tmp`
+pub struct UrlProvider {
+    intranet_hosts: Vec<String>,
+}
+
+impl UrlProvider {
+    pub fn new(intranet_hosts: Vec<String>) -> UrlProvider {
+        UrlProvider { intranet_hosts }
+    }
+}
+
+impl QueryProvider for UrlProvider {
+    fn name(&self) -> &'static str {
+        "Web Browsing"
+    }
+
+    fn priority(&self) -> u8 {
+        40
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        parse_url(&query.raw, &self.intranet_hosts).is_some()
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        let Some(url) = parse_url(&query.raw, &self.intranet_hosts) else {
+            return vec![];
+        };
+        vec![App {
+            open_command: AppCommand::Function(Function::OpenWebsite(url.to_string())),
+            desc: self.name().to_string(),
+            icons: None,
+            name: "Open Website: ".to_string() + query.raw.trim(),
+            name_lc: "".to_string(),
+        }]
+    }
+}
+
+/// Searches the engine whose keyword the query starts with, like `gh rustcast` or `crates serde`
+pub struct SearchEngineProvider {
+    engines: Vec<Engine>,
+}
+
+impl SearchEngineProvider {
+    pub fn new(engines: &[SearchEngine]) -> SearchEngineProvider {
+        SearchEngineProvider {
+            engines: engines.iter().map(Engine::from_config).collect(),
+        }
+    }
+
+    /// The engine that the query is for, and the search arguments after its keyword
+    fn split<'a>(&self, query: &'a Query) -> Option<(&Engine, &'a str)> {
+        let (keyword, args) = query.raw.trim().split_once(' ')?;
+        let args = args.trim();
+        let engine = self
+            .engines
+            .iter()
+            .find(|engine| engine.keyword == keyword.to_lowercase())?;
+        (!args.is_empty()).then_some((engine, args))
+    }
+}
+
+impl QueryProvider for SearchEngineProvider {
+    fn name(&self) -> &'static str {
+        "Search Engines"
+    }
+
+    fn priority(&self) -> u8 {
+        70
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        self.split(query).is_some()
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        self.split(query)
+            .map(|(engine, args)| vec![engine.row(args)])
+            .unwrap_or_default()
+    }
+}
+
+/// Searches the web for questions (ending in `?`) and queries with several words, with the
+/// default search engine
+pub struct WebSearchProvider {
+    engine: Engine,
+}
+
+impl WebSearchProvider {
+    /// The `default_search_engine` from the config, or the `search_url` if there isn't one
+    pub fn new(config: &Config) -> WebSearchProvider {
+        let engine = config
+            .default_search_engine
+            .as_ref()
+            .and_then(|keyword| {
+                config
+                    .search_engines
+                    .iter()
+                    .find(|engine| engine.keyword.eq_ignore_ascii_case(keyword))
+            })
+            .map(Engine::from_config)
+            .unwrap_or_else(|| Engine {
+                keyword: String::new(),
+                name: "the web".to_string(),
+                url: config.search_url.clone(),
+                icon: None,
+            });
+        WebSearchProvider { engine }
+    }
+}
+
+impl QueryProvider for WebSearchProvider {
+    fn name(&self) -> &'static str {
+        "Web Search"
+    }
+
+    fn priority(&self) -> u8 {
+        10
+    }
+
+    fn matches(&self, query: &Query) -> bool {
+        query.lower.ends_with('?') || query.lower.split(' ').count() > 1
+    }
+
+    fn produce(&self, query: &Query) -> Vec<App> {
+        let args = query.raw.trim();
+        vec![self.engine.row(args.strip_suffix('?').unwrap_or(args))]
+    }
+}
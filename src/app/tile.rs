@@ -2,11 +2,20 @@
 pub mod elm;
 pub mod update;
 
-use crate::app::{ArrowKey, Message, Move, Page};
+use crate::app::pages::generic::{Section, ViewState};
+use crate::app::pages::shell_output::ShellRun;
+use crate::app::providers::{APPS_PRIORITY, PINNED_PRIORITY, ProviderRegistry, Query, recent};
+use crate::app::{ArrowKey, Message, Move, Page, Toggle};
 use crate::clipboard::ClipBoardContentType;
-use crate::config::Config;
-use crate::utils::open_settings;
-use crate::{app::apps::App, platform::default_app_paths};
+use crate::config::{Config, HomeSection, Shelly};
+use crate::extensions::wasm;
+use crate::scripts;
+use crate::shell::stream_output;
+use crate::utils::{self, open_settings};
+use crate::{
+    app::apps::{Action, App, AppCommand},
+    platform::{self, default_app_paths},
+};
 
 use arboard::Clipboard;
 use global_hotkey::hotkey::HotKey;
@@ -20,6 +29,7 @@ use iced::{
     keyboard::{self, key::Named},
     stream,
 };
+use iced::{Task, task};
 use iced::{event, window};
 
 use objc2::rc::Retained;
@@ -28,9 +38,18 @@ use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use tray_icon::TrayIcon;
 
 use std::fs;
-use std::ops::Bound;
+use std::sync::Arc;
 use std::time::Duration;
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+/// How many launched apps are remembered for the home screen
+const MAX_LAUNCHED: usize = 20;
+
+/// How often the config, apps, scripts and WASM extensions are checked for changes
+const HOT_RELOAD_INTERVAL: Duration = Duration::from_millis(500);
 
 /// This is a wrapper around the sender to disable dropping
 #[derive(Clone, Debug)]
@@ -42,18 +61,84 @@ impl Drop for ExtSender {
 }
 
 /// All the indexed apps that rustcast can search for
-#[derive(Clone, Debug)]
+///
+/// Apps are found by their `name_lc`, and by the aliases that the config gives them, and the
+/// pinned ones come before the rest
+#[derive(Clone, Debug, Default)]
 struct AppIndex {
     by_name: BTreeMap<String, App>,
+    /// The aliases from the config, with the `name_lc`s of the apps that they stand for
+    aliases: BTreeMap<String, String>,
+    /// The `name_lc`s of the pinned apps
+    pinned: HashSet<String>,
 }
 
 impl AppIndex {
     /// Search for an element in the index that starts with the provided prefix
+    ///
+    /// The apps with an alias that starts with the prefix come before the others, and the pinned
+    /// apps before all of them
     fn search_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a App> + 'a {
-        self.by_name
-            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
-            .take_while(move |(k, _)| k.starts_with(prefix))
-            .map(|(_, v)| v)
+        let by_alias = utils::search_prefix(
+            self.aliases
+                .iter()
+                .map(|(alias, name_lc)| (alias.as_str(), name_lc)),
+            prefix,
+        )
+        .into_iter()
+        .filter_map(|name_lc| self.by_name.get(name_lc));
+        let by_name = utils::search_prefix(
+            self.by_name
+                .iter()
+                .map(|(name_lc, app)| (name_lc.as_str(), app)),
+            prefix,
+        );
+
+        let mut found = by_alias.chain(by_name).collect::<Vec<_>>();
+        if !self.aliases.is_empty() {
+            let mut seen = HashSet::new();
+            found.retain(|app| seen.insert(&app.name_lc));
+        }
+        if !self.pinned.is_empty() {
+            found.sort_by_key(|app| !self.is_pinned(app));
+        }
+        found.into_iter()
+    }
+
+    /// Find an app by its name, or by the name that it's indexed by (like the alias of a
+    /// quicklink), ignoring case
+    fn find(&self, name: &str) -> Option<&App> {
+        let name = name.to_lowercase();
+        self.by_name.get(&name).or_else(|| {
+            self.by_name
+                .values()
+                .find(|app| app.name.to_lowercase() == name)
+        })
+    }
+
+    /// Whether the app is in the index, rather than being a row of a provider
+    fn contains(&self, app: &App) -> bool {
+        self.by_name.get(&app.name_lc) == Some(app)
+    }
+
+    fn is_pinned(&self, app: &App) -> bool {
+        self.pinned.contains(&app.name_lc)
+    }
+
+    /// Use the aliases and favourites of the config, which are the names of the apps
+    fn apply_config(&mut self, config: &Config) {
+        self.aliases = config
+            .aliases
+            .iter()
+            .filter_map(|(alias, name)| {
+                Some((alias.to_lowercase(), self.find(name)?.name_lc.clone()))
+            })
+            .collect();
+        self.pinned = config
+            .favourites
+            .iter()
+            .filter_map(|name| Some(self.find(name)?.name_lc.clone()))
+            .collect();
     }
 
     /// Factory function for creating
@@ -63,7 +148,10 @@ impl AppIndex {
             bmap.insert(app.name_lc.clone(), app);
         }
 
-        AppIndex { by_name: bmap }
+        AppIndex {
+            by_name: bmap,
+            ..Default::default()
+        }
     }
 }
 
@@ -78,10 +166,27 @@ impl AppIndex {
 /// - Visible (bool) whether the window is visible or not
 /// - Focused (bool) whether the window is focused or not
 /// - Frontmost ([`Option<Retained<NSRunningApplication>>`]) the frontmost application before the window was opened
+/// - Selection (`Option<String>`) the text that was selected in the frontmost application
 /// - Config ([`Config`]) the app's config
 /// - Open Hotkey ID (`u32`) the id of the hotkey that opens the window
 /// - Clipboard Content (`Vec<`[`ClipBoardContentType`]`>`) all of the cliboard contents
 /// - Page ([`Page`]) the current page of the window (main or clipboard history)
+/// - Providers ([`ProviderRegistry`]) what the query is run through, besides the app index
+/// - Search Generation (`u64`) which query the running providers are for, so that the rows of
+///   older queries can be dropped
+/// - Ranked Results (`Vec<(u8, Vec<`[`App`]`>)>`) the rows that make up the results, by priority
+/// - Pending Searches (`usize`) how many providers are still running
+/// - Search Handles (`Vec<`[`task::Handle`]`>`) the handles to abort the running providers
+/// - Shell Run (`Option<`[`ShellRun`]`>`) the shell command whose output is shown
+/// - Back (`Vec<`[`PageState`]`>`) the pages that were left for the current one, so that escape
+///   can go back to them
+/// - Modifiers ([`Modifiers`]) the modifier keys that are held, which pick the action that enter
+///   runs
+/// - Views (`Vec<`[`ViewState`]`>`) the views of the [`Page::View`] pages on the stack, in the
+///   same order
+/// - Launched (`Vec<`[`App`]`>`) the apps and commands that were launched, most recent first
+/// - Home (`Vec<`[`Section`]`>`) the sections of the home screen, whose rows are the results while
+///   the query is empty
 #[derive(Clone)]
 pub struct Tile {
     pub theme: iced::Theme,
@@ -94,6 +199,7 @@ pub struct Tile {
     visible: bool,
     focused: bool,
     frontmost: Option<Retained<NSRunningApplication>>,
+    selection: Option<String>,
     pub config: Config,
     /// The opening hotkey
     hotkey: HotKey,
@@ -102,6 +208,34 @@ pub struct Tile {
     tray_icon: Option<TrayIcon>,
     sender: Option<ExtSender>,
     page: Page,
+    providers: Arc<ProviderRegistry>,
+    search_generation: u64,
+    ranked_results: Vec<RankedRows>,
+    pending_searches: usize,
+    search_handles: Vec<task::Handle>,
+    shell_run: Option<ShellRun>,
+    back: Vec<PageState>,
+    views: Vec<ViewState>,
+    modifiers: Modifiers,
+    launched: Vec<App>,
+    home: Vec<Section>,
+}
+
+/// The rows of the results that came from one source (a provider, or the app index), which are
+/// ranked by its priority
+#[derive(Debug, Clone)]
+struct RankedRows {
+    source: String,
+    priority: u8,
+    rows: Vec<App>,
+}
+
+/// A page that was left for another one, with its query and focus as they were
+#[derive(Debug, Clone)]
+pub struct PageState {
+    page: Page,
+    query: String,
+    focus_id: u32,
 }
 
 impl Tile {
@@ -125,6 +259,14 @@ impl Tile {
                 key: keyboard::Key::Named(keyboard::key::Named::Escape),
                 ..
             }) => Some(Message::EscKeyPressed(id)),
+            iced::Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                Some(Message::ModifiersChanged(modifiers))
+            }
+            iced::Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Character(cha),
+                modifiers,
+                ..
+            }) if modifiers.command() && cha.to_lowercase() == "k" => Some(Message::ShowActions),
             iced::Event::Keyboard(keyboard::Event::KeyPressed {
                 key: keyboard::Key::Character(cha),
                 modifiers: Modifiers::LOGO,
@@ -167,6 +309,8 @@ impl Tile {
                                 return Some(Message::ReloadConfig);
                             } else if modifiers.command() && chr.to_string() == "," {
                                 open_settings();
+                            } else if modifiers.command() && chr.to_lowercase() == "k" {
+                                // The action panel is opened with the other shortcuts
                             } else {
                                 return Some(Message::FocusTextInput(Move::Forwards(
                                     chr.to_string(),
@@ -222,12 +366,343 @@ impl Tile {
         self.results = results;
     }
 
+    /// Cancel the providers that are still running for the previous query, so that their rows
+    /// are never shown
+    pub fn cancel_search(&mut self) {
+        for handle in self.search_handles.drain(..) {
+            handle.abort();
+        }
+        self.search_generation += 1;
+        self.pending_searches = 0;
+        self.ranked_results.clear();
+    }
+
+    /// Run every provider that matches the query as its own task, alongside the apps that are
+    /// already in the results
+    ///
+    /// The rows come back through [`Message::ProviderResults`], tagged with the generation of the
+    /// query
+    pub fn start_search(&mut self, id: window::Id) -> Task<Message> {
+        let query = Query::new(&self.query);
+        let generation = self.search_generation;
+        let (pinned, apps) = self
+            .results
+            .iter()
+            .cloned()
+            .partition(|app| self.options.is_pinned(app));
+        self.ranked_results = vec![
+            RankedRows {
+                source: "Pinned".to_string(),
+                priority: PINNED_PRIORITY,
+                rows: pinned,
+            },
+            RankedRows {
+                source: "Apps".to_string(),
+                priority: APPS_PRIORITY,
+                rows: apps,
+            },
+        ];
+
+        let providers = self.providers.matching(&query);
+        self.pending_searches = providers.len();
+        Task::batch(providers.into_iter().map(|provider| {
+            let query = query.clone();
+            let source = provider.name().to_string();
+            let priority = provider.priority();
+            let (task, handle) = Task::perform(
+                async move {
+                    tokio::task::spawn_blocking(move || provider.produce(&query))
+                        .await
+                        .unwrap_or_default()
+                },
+                move |rows| Message::ProviderResults(generation, source, priority, rows, id),
+            )
+            .abortable();
+            self.search_handles.push(handle);
+            task
+        }))
+    }
+
+    /// Add the rows of a provider to the results, after every row with the same or a higher
+    /// priority
+    ///
+    /// Returns false if the rows are for an older query
+    pub fn insert_results(
+        &mut self,
+        generation: u64,
+        source: &str,
+        priority: u8,
+        rows: Vec<App>,
+    ) -> bool {
+        if generation != self.search_generation {
+            return false;
+        }
+        self.pending_searches = self.pending_searches.saturating_sub(1);
+        self.add_rows(source, priority, rows);
+        true
+    }
+
+    /// Rank rows in with the results by their priority, replacing the rows that came from the
+    /// same source before
+    ///
+    /// Once the user has moved the focus off the first row it stays on the same row, so rows that
+    /// arrive above it push it down instead of sliding something else under it
+    pub fn add_rows(&mut self, source: &str, priority: u8, rows: Vec<App>) {
+        let replaced = self
+            .ranked_results
+            .iter()
+            .position(|ranked| ranked.source == source);
+        let removed = replaced.map_or(0, |index| self.ranked_results.remove(index).rows.len());
+        if rows.is_empty() && removed == 0 {
+            return;
+        }
+
+        let position = replaced.unwrap_or_else(|| {
+            self.ranked_results
+                .iter()
+                .position(|ranked| ranked.priority < priority)
+                .unwrap_or(self.ranked_results.len())
+        });
+        let above = self.ranked_results[..position]
+            .iter()
+            .map(|ranked| ranked.rows.len())
+            .sum::<usize>();
+        let focus = self.focus_id as usize;
+        if self.page == Page::Main && focus > 0 && focus >= above + removed {
+            self.focus_id = (focus + rows.len() - removed) as u32;
+        }
+
+        if !rows.is_empty() {
+            self.ranked_results.insert(
+                position,
+                RankedRows {
+                    source: source.to_string(),
+                    priority,
+                    rows,
+                },
+            );
+        }
+        self.results = self
+            .ranked_results
+            .iter()
+            .flat_map(|ranked| ranked.rows.iter().cloned())
+            .collect();
+        if self.page == Page::Main {
+            self.focus_id = self
+                .focus_id
+                .min(self.results.len().saturating_sub(1) as u32);
+        }
+    }
+
+    /// Run a shell command with its output shown inline, on the shell output page
+    pub fn start_shell_output(&mut self, shelly: Shelly) -> Task<Message> {
+        self.close_shell_output();
+        let (task, handle) = Task::run(
+            stream_output(shelly.clone(), self.query.clone()),
+            Message::ShellOutput,
+        )
+        .abortable();
+        self.shell_run = Some(ShellRun::new(shelly.alias, handle));
+        self.push_page(Page::ShellOutput);
+        task
+    }
+
+    /// Go to a page, and remember the current one so that escape can go back to it
+    ///
+    /// The output of a shell command isn't remembered, as it is stopped when it is left
+    pub fn push_page(&mut self, page: Page) {
+        if self.page != Page::ShellOutput {
+            self.back.push(PageState {
+                page: self.page.clone(),
+                query: self.query.clone(),
+                focus_id: self.focus_id,
+            });
+        }
+        self.page = page;
+    }
+
+    /// The row that is focused, on the pages that are made of apps
+    pub fn focused_app(&self) -> Option<App> {
+        match self.page {
+            Page::Main | Page::EmojiSearch => self.results.get(self.focus_id as usize).cloned(),
+            Page::View => self
+                .views
+                .last()?
+                .rows(&self.query_lc)
+                .get(self.focus_id as usize)
+                .map(|app| (*app).clone()),
+            Page::ClipboardHistory | Page::ShellOutput => None,
+        }
+    }
+
+    /// Remember an app or command that was launched from the results, for the home screen
+    pub fn record_launch(&mut self, app: App) {
+        if !self.options.contains(&app) {
+            return;
+        }
+        self.launched.retain(|launched| *launched != app);
+        self.launched.insert(0, app);
+        self.launched.truncate(MAX_LAUNCHED);
+    }
+
+    /// What can be done with a row, which for the apps in the index includes pinning them and
+    /// giving them an alias
+    pub fn actions(&self, app: &App) -> Vec<Action> {
+        let mut actions = app.open_command.actions();
+        if self.options.contains(app) {
+            let pin = if self.options.is_pinned(app) {
+                "Unpin"
+            } else {
+                "Pin"
+            };
+            actions.extend([
+                Action::new(
+                    pin,
+                    AppCommand::Message(Message::TogglePin(app.name.clone())),
+                ),
+                Action::new(
+                    "Set Alias",
+                    AppCommand::Message(Message::PushView(app.alias_view(self.alias_of(app)))),
+                ),
+            ]);
+        }
+        actions
+    }
+
+    /// The alias that the config gives an app, if it has one
+    fn alias_of(&self, app: &App) -> Option<&str> {
+        self.config
+            .aliases
+            .iter()
+            .find(|(_, name)| self.options.find(name) == Some(app))
+            .map(|(alias, _)| alias.as_str())
+    }
+
+    /// Pin an app to the top of the results and the home screen, or unpin it if it's pinned
+    pub fn toggle_pin(&mut self, name: &str) {
+        let Some(app) = self.options.find(name).cloned() else {
+            return;
+        };
+        let options = &self.options;
+        let count = self.config.favourites.len();
+        self.config
+            .favourites
+            .retain(|favourite| options.find(favourite) != Some(&app));
+        if self.config.favourites.len() == count {
+            self.config.favourites.push(app.name);
+        }
+        self.options.apply_config(&self.config);
+    }
+
+    /// Give an app an alias instead of the one that it had, or take its alias away if the new
+    /// one is empty
+    pub fn set_alias(&mut self, name: &str, alias: &str) {
+        let Some(app) = self.options.find(name).cloned() else {
+            return;
+        };
+        let options = &self.options;
+        self.config
+            .aliases
+            .retain(|_, target| options.find(target) != Some(&app));
+        let alias = alias.trim().to_lowercase();
+        if !alias.is_empty() {
+            self.config.aliases.insert(alias, app.name);
+        }
+        self.options.apply_config(&self.config);
+    }
+
+    /// Fill the sections of the home screen in, and make their rows the results
+    pub fn load_home(&mut self) {
+        let config = &self.config;
+        self.home = config
+            .home
+            .sections
+            .iter()
+            .filter_map(|section| {
+                let (title, items): (_, Vec<App>) = match section {
+                    HomeSection::Favourites => (
+                        "Favourites",
+                        config
+                            .favourites
+                            .iter()
+                            .filter_map(|name| self.options.find(name).cloned())
+                            .collect(),
+                    ),
+                    HomeSection::Recent => ("Recently Launched", self.launched.clone()),
+                    HomeSection::Files if config.recent_files.enabled => {
+                        ("Recent Files", recent::rows(""))
+                    }
+                    HomeSection::Files => return None,
+                    HomeSection::Clipboard => (
+                        "Clipboard",
+                        self.clipboard_content
+                            .iter()
+                            .take(config.home.rows)
+                            .map(|content| content.to_app())
+                            .collect(),
+                    ),
+                    HomeSection::Toggles => (
+                        "Quick Toggles",
+                        Toggle::ALL
+                            .iter()
+                            .map(|toggle| toggle.to_app(config))
+                            .collect(),
+                    ),
+                };
+                let items = items.into_iter().take(config.home.rows).collect::<Vec<_>>();
+                (!items.is_empty()).then(|| Section {
+                    title: Some(title.to_string()),
+                    items,
+                })
+            })
+            .collect();
+        self.results = self
+            .home
+            .iter()
+            .flat_map(|section| section.items.iter().cloned())
+            .collect();
+    }
+
+    /// Forget the pages and views on the stack, like when the window is hidden
+    pub fn clear_pages(&mut self) {
+        self.back.clear();
+        self.views.clear();
+    }
+
+    /// Where the current page is, like `Main › Clipboard › Transform`
+    pub fn breadcrumbs(&self) -> String {
+        let mut views = self.views.iter();
+        self.back
+            .iter()
+            .map(|state| &state.page)
+            .chain([&self.page])
+            .map(|page| match page {
+                Page::View => views.next().map_or(page.name(), |state| state.view.title()),
+                page => page.name(),
+            })
+            .collect::<Vec<_>>()
+            .join(" › ")
+    }
+
+    /// Stop the shell command whose output is shown, and forget its output
+    pub fn close_shell_output(&mut self) {
+        if let Some(mut run) = self.shell_run.take() {
+            run.cancel();
+        }
+    }
+
+    /// Whether some providers are still working on the query
+    pub fn is_searching(&self) -> bool {
+        self.pending_searches > 0
+    }
+
     /// Gets the frontmost application to focus later.
     pub fn capture_frontmost(&mut self) {
         use objc2_app_kit::NSWorkspace;
 
         let ws = NSWorkspace::sharedWorkspace();
         self.frontmost = ws.frontmostApplication();
+        self.selection = platform::selected_text();
     }
 
     /// Restores the frontmost application.
@@ -241,7 +716,8 @@ impl Tile {
     }
 }
 
-/// This is the subscription function that handles hot reloading of the config
+/// This is the subscription function that handles hot reloading of the config (and the apps,
+/// scripts and WASM extensions)
 fn handle_hot_reloading() -> impl futures::Stream<Item = Message> {
     stream::channel(100, async |mut output| {
         let mut content = fs::read_to_string(
@@ -249,6 +725,9 @@ fn handle_hot_reloading() -> impl futures::Stream<Item = Message> {
         )
         .unwrap_or("".to_string());
 
+        let mut scripts = scripts::scan();
+        let mut extensions = wasm::scan();
+
         let paths = default_app_paths();
         let mut total_files: usize = paths
             .par_iter()
@@ -256,6 +735,8 @@ fn handle_hot_reloading() -> impl futures::Stream<Item = Message> {
             .sum();
 
         loop {
+            tokio::time::sleep(HOT_RELOAD_INTERVAL).await;
+
             let current_content = fs::read_to_string(
                 std::env::var("HOME").unwrap_or("".to_owned()) + "/.config/rustcast/config.toml",
             )
@@ -266,15 +747,23 @@ fn handle_hot_reloading() -> impl futures::Stream<Item = Message> {
                 .map(|dir| count_dirs_in_dir(Path::new(dir)))
                 .sum();
 
-            if current_content != content {
-                content = current_content;
-                output.send(Message::ReloadConfig).await.unwrap();
-            } else if total_files != current_total_files {
-                total_files = current_total_files;
+            let current_scripts = scripts::scan();
+            let current_extensions = wasm::scan();
+
+            // Everything is compared before reloading once, so that changes to several of them
+            // at once don't reload twice
+            let changed = current_content != content
+                || current_total_files != total_files
+                || current_scripts != scripts
+                || current_extensions != extensions;
+            content = current_content;
+            total_files = current_total_files;
+            scripts = current_scripts;
+            extensions = current_extensions;
+
+            if changed {
                 output.send(Message::ReloadConfig).await.unwrap();
             }
-
-            tokio::time::sleep(Duration::from_millis(10)).await;
         }
     })
 }
@@ -3,16 +3,22 @@
 
 use global_hotkey::hotkey::HotKey;
 use iced::border::Radius;
+use iced::keyboard::Modifiers;
 use iced::widget::scrollable::{Anchor, Direction, Scrollbar};
 use iced::widget::text::LineHeight;
-use iced::widget::{Column, Row, Scrollable, Text, container, space};
-use iced::{Alignment, Color, Length, Vector, window};
+use iced::widget::{Column, Row, Scrollable, Text, container, space, stack};
+use iced::{Alignment, Color, Length, Padding, Vector, window};
 use iced::{Element, Task};
 use iced::{Length::Fill, widget::text_input};
 
 use rayon::slice::ParallelSliceMut;
 
+use std::sync::Arc;
+
 use crate::app::pages::emoji::emoji_page;
+use crate::app::pages::generic::{generic_view, list};
+use crate::app::pages::shell_output::shell_output_view;
+use crate::app::providers::ProviderRegistry;
 use crate::app::tile::AppIndex;
 use crate::config::Theme;
 use crate::styles::{contents_style, rustcast_text_input_style, tint, with_alpha};
@@ -23,6 +29,7 @@ use crate::{
     config::Config,
     platform::transform_process_to_ui_element,
 };
+use crate::{scripts, unit_conversion};
 
 /// Initialise the base window
 pub fn new(hotkey: HotKey, config: &Config) -> (Tile, Task<Message>) {
@@ -35,12 +42,18 @@ pub fn new(hotkey: HotKey, config: &Config) -> (Tile, Task<Message>) {
 
     let store_icons = config.theme.show_icons;
 
+    unit_conversion::load_currency_rates();
+    unit_conversion::load_custom_units(&config.units);
+
     let mut options = get_installed_apps(store_icons);
 
     options.extend(config.shells.iter().map(|x| x.to_app()));
+    options.extend(config.quicklinks.iter().map(|x| x.to_app()));
+    options.extend(scripts::load_scripts().iter().map(|x| x.to_app()));
     options.extend(App::basic_apps());
     options.par_sort_by_key(|x| x.name.len());
-    let options = AppIndex::from_apps(options);
+    let mut options = AppIndex::from_apps(options);
+    options.apply_config(config);
 
     (
         Tile {
@@ -57,6 +70,7 @@ pub fn new(hotkey: HotKey, config: &Config) -> (Tile, Task<Message>) {
                 .clone()
                 .and_then(|x| x.parse::<HotKey>().ok()),
             frontmost: None,
+            selection: None,
             focused: false,
             config: config.clone(),
             theme: config.theme.to_owned().into(),
@@ -64,6 +78,17 @@ pub fn new(hotkey: HotKey, config: &Config) -> (Tile, Task<Message>) {
             tray_icon: None,
             sender: None,
             page: Page::Main,
+            providers: Arc::new(ProviderRegistry::from_config(config)),
+            search_generation: 0,
+            ranked_results: vec![],
+            pending_searches: 0,
+            search_handles: vec![],
+            shell_run: None,
+            back: vec![],
+            views: vec![],
+            modifiers: Modifiers::empty(),
+            launched: vec![],
+            home: vec![],
         },
         Task::batch([open.map(|_| Message::OpenWindow)]),
     )
@@ -74,6 +99,7 @@ pub fn view(tile: &Tile, wid: window::Id) -> Element<'_, Message> {
         let round_bottom_edges = match &tile.page {
             Page::Main | Page::EmojiSearch => tile.results.is_empty(),
             Page::ClipboardHistory => tile.clipboard_content.is_empty(),
+            Page::ShellOutput | Page::View => false,
         };
         let title_input = text_input(tile.config.placeholder.as_str(), &tile.query)
             .on_input(move |a| Message::SearchQueryChanged(a, wid))
@@ -83,8 +109,28 @@ pub fn view(tile: &Tile, wid: window::Id) -> Element<'_, Message> {
             .id("query")
             .width(Fill)
             .line_height(LineHeight::Relative(1.75))
-            .style(move |_, _| rustcast_text_input_style(&tile.config.theme, round_bottom_edges))
-            .padding(20);
+            .style(move |_, _| rustcast_text_input_style(&tile.config.theme, round_bottom_edges));
+
+        // The breadcrumbs are shown at the end of the input, once a page has been gone to
+        let header: Element<'_, Message> = if tile.back.is_empty() {
+            title_input.padding(20).into()
+        } else {
+            let breadcrumbs = tile.breadcrumbs();
+            let width = breadcrumbs.chars().count() as f32 * 7.;
+            stack![
+                title_input.padding(Padding::new(20.).right(width + 30.)),
+                container(
+                    Text::new(breadcrumbs)
+                        .size(12)
+                        .font(tile.config.theme.font())
+                        .color(tile.config.theme.text_color(0.45)),
+                )
+                .align_right(Fill)
+                .center_y(Fill)
+                .padding([0, 20]),
+            ]
+            .into()
+        };
 
         let scrollbar_direction = if tile.config.theme.show_scroll_bar {
             Direction::Vertical(
@@ -97,7 +143,22 @@ pub fn view(tile: &Tile, wid: window::Id) -> Element<'_, Message> {
             Direction::Vertical(Scrollbar::hidden())
         };
 
-        let results = if tile.page == Page::ClipboardHistory {
+        // The focused row shows how to open its action panel, if the panel has more than one action
+        let has_actions = tile
+            .focused_app()
+            .is_some_and(|app| tile.actions(&app).len() > 1);
+
+        let results = if let (Page::ShellOutput, Some(run)) = (&tile.page, &tile.shell_run) {
+            shell_output_view(run, tile.config.theme.clone())
+        } else if let (Page::View, Some(state)) = (&tile.page, tile.views.last()) {
+            generic_view(
+                state,
+                &tile.query_lc,
+                tile.config.theme.clone(),
+                tile.focus_id,
+                has_actions,
+            )
+        } else if tile.page == Page::ClipboardHistory {
             clipboard_view(
                 tile.clipboard_content.clone(),
                 tile.focus_id,
@@ -115,23 +176,46 @@ pub fn view(tile: &Tile, wid: window::Id) -> Element<'_, Message> {
                     .collect(),
                 tile.focus_id,
             )
+        } else if tile.page == Page::Main && tile.query_lc.is_empty() && !tile.home.is_empty() {
+            list(
+                &tile.home,
+                "",
+                tile.config.theme.clone(),
+                tile.focus_id,
+                has_actions,
+            )
         } else {
             container(Column::from_iter(tile.results.iter().enumerate().map(
                 |(i, app)| {
-                    app.clone()
-                        .render(tile.config.theme.clone(), i as u32, tile.focus_id)
+                    app.clone().render(
+                        tile.config.theme.clone(),
+                        i as u32,
+                        tile.focus_id,
+                        has_actions,
+                    )
                 },
             )))
             .into()
         };
 
-        let results_count = match &tile.page {
-            Page::Main => tile.results.len(),
-            Page::ClipboardHistory => tile.clipboard_content.len(),
-            Page::EmojiSearch => tile.results.len(),
+        let footer_text = match &tile.page {
+            Page::Main if tile.query_lc.is_empty() && !tile.home.is_empty() => {
+                Some("Type to search".to_string())
+            }
+            Page::Main => results_footer(tile.results.len(), tile.is_searching()),
+            Page::ClipboardHistory => results_footer(tile.clipboard_content.len(), false),
+            Page::EmojiSearch => results_footer(tile.results.len(), false),
+            Page::ShellOutput => tile.shell_run.as_ref().map(|run| run.status()),
+            Page::View => tile
+                .views
+                .last()
+                .map(|state| format!("{}, esc to go back", state.view.title())),
         };
 
-        let height = if tile.page == Page::ClipboardHistory {
+        let height = if matches!(
+            tile.page,
+            Page::ClipboardHistory | Page::ShellOutput | Page::View
+        ) {
             385
         } else {
             std::cmp::min(tile.results.len() * 60, 290)
@@ -143,9 +227,9 @@ pub fn view(tile: &Tile, wid: window::Id) -> Element<'_, Message> {
 
         let contents = container(
             Column::new()
-                .push(title_input)
+                .push(header)
                 .push(scrollable)
-                .push(footer(tile.config.theme.clone(), results_count))
+                .push(footer(tile.config.theme.clone(), footer_text))
                 .spacing(0),
         )
         .style(|_| container::Style {
@@ -167,19 +251,33 @@ pub fn view(tile: &Tile, wid: window::Id) -> Element<'_, Message> {
     }
 }
 
-fn footer(theme: Theme, results_count: usize) -> Element<'static, Message> {
-    let text = if results_count == 0 {
-        return space().into();
+/// The footer text for a page of results
+fn results_footer(results_count: usize, searching: bool) -> Option<String> {
+    let text = if results_count == 0 && !searching {
+        return None;
+    } else if results_count == 0 {
+        "Searching…"
+    } else if results_count == 1 && searching {
+        "1 result found, searching…"
     } else if results_count == 1 {
         "1 result found"
+    } else if searching {
+        &format!("{} results found, searching…", results_count)
     } else {
         &format!("{} results found", results_count)
     };
+    Some(text.to_string())
+}
+
+fn footer(theme: Theme, text: Option<String>) -> Element<'static, Message> {
+    let Some(text) = text else {
+        return space().into();
+    };
 
     container(
         Row::new()
             .push(
-                Text::new(text.to_string())
+                Text::new(text)
                     .size(12)
                     .height(30)
                     .color(theme.text_color(0.7))
@@ -2,10 +2,11 @@
 use std::cmp::min;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 use std::thread;
 
 use iced::Task;
-use iced::widget::image::Handle;
+use iced::keyboard::Modifiers;
 use iced::widget::operation;
 use iced::widget::operation::AbsoluteOffset;
 use iced::window;
@@ -13,21 +14,23 @@ use rayon::slice::ParallelSliceMut;
 
 use crate::app::WINDOW_WIDTH;
 use crate::app::apps::App;
-use crate::app::apps::AppCommand;
+use crate::app::apps::{ALIAS_FORM, AppCommand, open_with_view};
 use crate::app::default_settings;
 use crate::app::menubar::menu_icon;
+use crate::app::pages::generic::{View, ViewState};
+use crate::app::providers::ProviderRegistry;
 use crate::app::tile::AppIndex;
-use crate::app::{Message, Page, tile::Tile};
-use crate::calculator::Expr;
-use crate::clipboard::ClipBoardContentType;
+use crate::app::{Message, Page, Toggle, tile::Tile};
 use crate::commands::Function;
-use crate::config::Config;
-use crate::unit_conversion;
-use crate::utils::is_valid_url;
+use crate::config::{Config, OutputMode};
+use crate::platform::{apps_to_open, get_installed_apps};
+use crate::scripts::ARGUMENTS_FORM;
+use crate::shell::{quote, split_args};
+use crate::utils::text_after_alias;
 use crate::{app::ArrowKey, platform::focus_this_app};
 use crate::{app::DEFAULT_WINDOW_HEIGHT, platform::perform_haptic};
 use crate::{app::Move, platform::HapticPattern};
-use crate::{app::RUSTCAST_DESC_NAME, platform::get_installed_apps};
+use crate::{extensions, scripts, unit_conversion};
 
 pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
     match message {
@@ -36,19 +39,56 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
             focus_this_app();
             tile.focused = true;
             tile.visible = true;
-            Task::none()
+            if tile.page != Page::Main || !tile.query_lc.is_empty() {
+                return Task::none();
+            }
+            tile.load_home();
+            let count = tile.results.len();
+            window::latest().and_then(move |id| resize_for_home(id, count))
         }
         Message::HideTrayIcon => {
             tile.tray_icon = None;
             tile.config.show_trayicon = false;
-            let home = std::env::var("HOME").unwrap();
-            let confg_str = toml::to_string(&tile.config).unwrap();
-            thread::spawn(move || fs::write(home + "/.config/rustcast/config.toml", confg_str));
+            save_config(&tile.config);
             Task::none()
         }
 
+        Message::Toggle(toggle) => {
+            toggle.flip(&mut tile.config);
+            match toggle {
+                Toggle::TrayIcon if tile.config.show_trayicon => {
+                    tile.tray_icon = tile
+                        .sender
+                        .clone()
+                        .map(|sender| menu_icon(tile.hotkey, sender));
+                }
+                Toggle::TrayIcon => tile.tray_icon = None,
+                Toggle::HapticFeedback | Toggle::ScrollBar => {}
+            }
+            save_config(&tile.config);
+            tile.load_home();
+            Task::none()
+        }
+
+        Message::TogglePin(name) => {
+            tile.toggle_pin(&name);
+            save_config(&tile.config);
+            // The results are shown again with the app moved, leaving the action panel if it was
+            // pinned from there
+            let query = tile.query.clone();
+            let in_panel = !tile.back.is_empty();
+            window::latest().map(|x| x.unwrap()).map(move |id| {
+                if in_panel {
+                    Message::GoBack(id)
+                } else {
+                    Message::SearchQueryChanged(query.clone(), id)
+                }
+            })
+        }
+
         Message::SetSender(sender) => {
             tile.sender = Some(sender.clone());
+            extensions::set_sender(sender.0.clone());
             if tile.config.show_trayicon {
                 tile.tray_icon = Some(menu_icon(tile.hotkey, sender));
             }
@@ -60,6 +100,17 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
                 return Task::none();
             }
 
+            // The first escape stops a running command, and the next one leaves its output
+            if tile.page == Page::ShellOutput
+                && tile.shell_run.as_mut().is_some_and(|run| run.cancel())
+            {
+                return Task::none();
+            }
+            if !tile.back.is_empty() {
+                return go_back(tile, id);
+            }
+            tile.close_shell_output();
+
             if tile.query_lc.is_empty() {
                 Task::batch([
                     Task::done(Message::HideWindow(id)),
@@ -67,18 +118,12 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
                 ])
             } else {
                 tile.page = Page::Main;
-
-                Task::batch(vec![
-                    Task::done(Message::ClearSearchQuery),
-                    Task::done(Message::ClearSearchResults),
-                    window::resize(
-                        id,
-                        iced::Size {
-                            width: WINDOW_WIDTH,
-                            height: DEFAULT_WINDOW_HEIGHT,
-                        },
-                    ),
-                ])
+                tile.query = String::new();
+                tile.query_lc = String::new();
+                tile.focus_id = 0;
+                tile.cancel_search();
+                tile.load_home();
+                resize_for_home(id, tile.results.len())
             }
         }
 
@@ -92,6 +137,11 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
             let len = match tile.page {
                 Page::ClipboardHistory => tile.clipboard_content.len() as u32,
                 Page::EmojiSearch => tile.emoji_apps.search_prefix(&tile.query_lc).count() as u32, // or tile.results.len()
+                Page::ShellOutput => 0, // the output isn't made of rows
+                Page::View => tile
+                    .views
+                    .last()
+                    .map_or(0, |state| state.rows(&tile.query_lc).len() as u32),
                 _ => tile.results.len() as u32,
             };
 
@@ -101,8 +151,10 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
                 return Task::none();
             }
 
+            let columns = tile.views.last().map_or(1, |state| state.columns()) as u32;
             let change_by = match tile.page {
                 Page::EmojiSearch => 6,
+                Page::View => columns,
                 _ => 1,
             };
 
@@ -123,14 +175,25 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
                     tile.focus_id = (tile.focus_id + 1) % len;
                     operation::focus("results")
                 }
+                (ArrowKey::Left, Page::View) if columns > 1 => {
+                    tile.focus_id = (tile.focus_id + len - 1) % len;
+                    Task::none()
+                }
+                (ArrowKey::Right, Page::View) if columns > 1 => {
+                    tile.focus_id = (tile.focus_id + 1) % len;
+                    Task::none()
+                }
                 _ => Task::none(),
             };
 
             let direction = if tile.focus_id < old_focus_id { -1 } else { 1 };
             let quantity = match tile.page {
-                Page::Main => 66.5,
+                Page::Main | Page::ShellOutput => 66.5,
                 Page::ClipboardHistory => 50.,
                 Page::EmojiSearch => 5.,
+                // A row of cells is about as tall as a cell is wide
+                Page::View if columns > 1 => WINDOW_WIDTH / (columns * columns) as f32,
+                Page::View => 66.5,
             };
 
             Task::batch([
@@ -145,22 +208,62 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
             ])
         }
 
-        Message::OpenFocused => match tile.results.get(tile.focus_id as usize) {
-            Some(App {
-                open_command: AppCommand::Function(func),
-                ..
-            }) => Task::done(Message::RunFunction(func.to_owned())),
-            Some(App {
-                open_command: AppCommand::Message(msg),
-                ..
-            }) => Task::done(msg.to_owned()),
-            Some(App {
-                open_command: AppCommand::Display,
+        Message::OpenFocused => {
+            if let Some(ViewState {
+                view: View::Form { .. },
                 ..
-            }) => Task::done(Message::ReturnFocus),
+            }) = tile.views.last()
+                && tile.page == Page::View
+            {
+                return Task::done(Message::SubmitForm);
+            }
+            let Some(app) = tile.focused_app() else {
+                return Task::none();
+            };
+
+            // The modifiers that are held pick one of the first few actions
+            let index = if tile.modifiers.command() {
+                1
+            } else if tile.modifiers.alt() {
+                2
+            } else {
+                0
+            };
+            match tile.actions(&app).get(index) {
+                Some(action) => {
+                    Task::done(action.command.message().unwrap_or(Message::ReturnFocus))
+                }
+                None => Task::none(),
+            }
+        }
+
+        Message::ShowActions => match tile.focused_app() {
+            Some(app) => Task::done(Message::PushView(app.actions_view(tile.actions(&app)))),
             None => Task::none(),
         },
 
+        Message::ShowOpenWith(path) => {
+            let apps = apps_to_open(&path, tile.config.theme.show_icons);
+            Task::done(Message::PushView(open_with_view(&path, apps)))
+        }
+
+        Message::PromptArguments(path) => {
+            let Some(script) = scripts::read_script(Path::new(&path)) else {
+                return Task::none();
+            };
+            let typed = split_args(text_after_alias(&tile.query, &script.alias));
+            if typed.len() >= script.arguments.len() {
+                let shelly = Box::new(script.to_shelly());
+                return Task::done(Message::RunFunction(Function::RunShellCommand(shelly)));
+            }
+            Task::done(Message::PushView(script.arguments_view(&typed)))
+        }
+
+        Message::ModifiersChanged(modifiers) => {
+            tile.modifiers = modifiers;
+            Task::none()
+        }
+
         Message::ReloadConfig => {
             let new_config: Config = match toml::from_str(
                 &fs::read_to_string(
@@ -173,14 +276,21 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
                 Err(_) => return Task::none(),
             };
 
+            unit_conversion::load_currency_rates();
+            unit_conversion::load_custom_units(&new_config.units);
+
             let mut new_options = get_installed_apps(new_config.theme.show_icons);
             new_options.extend(new_config.shells.iter().map(|x| x.to_app()));
+            new_options.extend(new_config.quicklinks.iter().map(|x| x.to_app()));
+            new_options.extend(scripts::load_scripts().iter().map(|x| x.to_app()));
             new_options.extend(App::basic_apps());
             new_options.par_sort_by_key(|x| x.name.len());
 
             tile.theme = new_config.theme.to_owned().into();
+            tile.providers = Arc::new(ProviderRegistry::from_config(&new_config));
             tile.config = new_config;
             tile.options = AppIndex::from_apps(new_options);
+            tile.options.apply_config(&tile.config);
             Task::none()
         }
 
@@ -224,20 +334,62 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
         }
 
         Message::SwitchToPage(page) => {
-            tile.page = page;
+            tile.close_shell_output();
+            if page == Page::Main {
+                tile.clear_pages();
+                tile.page = page;
+                tile.query = String::new();
+                tile.query_lc = String::new();
+                tile.cancel_search();
+                tile.load_home();
+                let count = tile.results.len();
+                return window::latest().and_then(move |id| resize_for_home(id, count));
+            } else if page != tile.page {
+                tile.push_page(page);
+            }
             Task::batch([
                 Task::done(Message::ClearSearchQuery),
                 Task::done(Message::ClearSearchResults),
             ])
         }
 
+        Message::RunFunction(Function::RunShellCommand(shelly))
+            if shelly.output == OutputMode::Inline && shelly.terminal.is_none() =>
+        {
+            let output = tile.start_shell_output(*shelly);
+            Task::batch([
+                output,
+                window::latest().and_then(|id| {
+                    window::resize(
+                        id,
+                        iced::Size {
+                            width: WINDOW_WIDTH,
+                            height: ((7 * 55) + 35 + DEFAULT_WINDOW_HEIGHT as usize) as f32,
+                        },
+                    )
+                }),
+            ])
+        }
+
         Message::RunFunction(command) => {
-            command.execute(&tile.config, &tile.query);
+            let launched = tile.results.iter().find(|app| {
+                matches!(&app.open_command, AppCommand::Function(function) if *function == command)
+            });
+            if tile.page == Page::Main
+                && let Some(app) = launched.cloned()
+            {
+                tile.record_launch(app);
+            }
+            command.execute(&tile.query, tile.selection.as_deref());
 
             let return_focus_task = match &command {
-                Function::OpenApp(_) | Function::OpenPrefPane | Function::GoogleSearch(_) => {
-                    Task::none()
-                }
+                Function::OpenApp(_)
+                | Function::OpenFile(_)
+                | Function::RevealInFinder(_)
+                | Function::OpenPrivateWindow(_)
+                | Function::OpenPrefPane
+                | Function::WebSearch(..)
+                | Function::OpenQuicklink(..) => Task::none(),
                 _ => Task::done(Message::ReturnFocus),
             };
 
@@ -253,9 +405,13 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
         }
 
         Message::HideWindow(a) => {
+            tile.close_shell_output();
             tile.visible = false;
             tile.focused = false;
             tile.page = Page::Main;
+            tile.clear_pages();
+            // The keys could be let go of once the window is closed, and never be seen
+            tile.modifiers = Modifiers::empty();
             Task::batch([window::close(a), Task::done(Message::ClearSearchResults)])
         }
 
@@ -285,7 +441,9 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
         }
 
         Message::ClearSearchResults => {
+            tile.cancel_search();
             tile.results = vec![];
+            tile.home = vec![];
             Task::none()
         }
         Message::WindowFocusChanged(wid, focused) => {
@@ -311,8 +469,21 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
 
             tile.query_lc = input.trim().to_lowercase();
             tile.query = input;
+            tile.cancel_search();
+            // Typing leaves the output of a shell command for the page that it was run from
+            if tile.page == Page::ShellOutput {
+                tile.close_shell_output();
+                tile.page = tile.back.pop().map_or(Page::Main, |state| state.page);
+            }
+            // A view filters its own rows as they are shown
+            if tile.page == Page::View {
+                return Task::none();
+            }
             let prev_size = tile.results.len();
-            if tile.query_lc.is_empty() && tile.page != Page::ClipboardHistory {
+            if tile.query_lc.is_empty() && tile.page == Page::Main {
+                tile.load_home();
+                return resize_for_home(id, tile.results.len());
+            } else if tile.query_lc.is_empty() && tile.page != Page::ClipboardHistory {
                 tile.results = vec![];
                 return window::resize(
                     id,
@@ -321,49 +492,6 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
                         height: DEFAULT_WINDOW_HEIGHT,
                     },
                 );
-            } else if tile.query_lc == "randomvar" {
-                let rand_num = rand::random_range(0..100);
-                tile.results = vec![App {
-                    open_command: AppCommand::Function(Function::RandomVar(rand_num)),
-                    desc: "Easter egg".to_string(),
-                    icons: None,
-                    name: rand_num.to_string(),
-                    name_lc: String::new(),
-                }];
-                return window::resize(
-                    id,
-                    iced::Size {
-                        width: WINDOW_WIDTH,
-                        height: 55. + DEFAULT_WINDOW_HEIGHT,
-                    },
-                );
-            } else if tile.query_lc == "67" {
-                tile.results = vec![App {
-                    open_command: AppCommand::Function(Function::RandomVar(67)),
-                    desc: "Easter egg".to_string(),
-                    icons: None,
-                    name: 67.to_string(),
-                    name_lc: String::new(),
-                }];
-                return window::resize(
-                    id,
-                    iced::Size {
-                        width: WINDOW_WIDTH,
-                        height: 55. + DEFAULT_WINDOW_HEIGHT,
-                    },
-                );
-            } else if tile.query_lc.ends_with("?") {
-                tile.results = vec![App {
-                    open_command: AppCommand::Function(Function::GoogleSearch(tile.query.clone())),
-                    icons: None,
-                    desc: "Web Search".to_string(),
-                    name: format!("Search for: {}", tile.query),
-                    name_lc: String::new(),
-                }];
-                return window::resize(
-                    id,
-                    iced::Size::new(WINDOW_WIDTH, 55. + DEFAULT_WINDOW_HEIGHT),
-                );
             } else if tile.query_lc == "cbhist" {
                 tile.page = Page::ClipboardHistory
             } else if tile.query_lc == "main" {
@@ -371,70 +499,6 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
             }
             tile.handle_search_query_changed();
 
-            if tile.results.is_empty()
-                && let Some(res) = Expr::from_str(&tile.query).ok()
-            {
-                tile.results.push(App {
-                    open_command: AppCommand::Function(Function::Calculate(res.clone())),
-                    desc: RUSTCAST_DESC_NAME.to_string(),
-                    icons: None,
-                    name: res.eval().map(|x| x.to_string()).unwrap_or("".to_string()),
-                    name_lc: "".to_string(),
-                });
-            } else if tile.results.is_empty()
-                && let Some(conversions) = unit_conversion::convert_query(&tile.query)
-            {
-                tile.results = conversions
-                    .into_iter()
-                    .map(|conversion| {
-                        let source = format!(
-                            "{} {}",
-                            unit_conversion::format_number(conversion.source_value),
-                            conversion.source_unit.name
-                        );
-                        let target = format!(
-                            "{} {}",
-                            unit_conversion::format_number(conversion.target_value),
-                            conversion.target_unit.name
-                        );
-                        App {
-                            open_command: AppCommand::Function(Function::CopyToClipboard(
-                                ClipBoardContentType::Text(target.clone()),
-                            )),
-                            desc: source,
-                            icons: None,
-                            name: target,
-                            name_lc: String::new(),
-                        }
-                    })
-                    .collect();
-            } else if tile.results.is_empty() && is_valid_url(&tile.query) {
-                tile.results.push(App {
-                    open_command: AppCommand::Function(Function::OpenWebsite(tile.query.clone())),
-                    desc: "Web Browsing".to_string(),
-                    icons: None,
-                    name: "Open Website: ".to_string() + &tile.query,
-                    name_lc: "".to_string(),
-                });
-            } else if tile.query_lc.split(' ').count() > 1 {
-                tile.results.push(App {
-                    open_command: AppCommand::Function(Function::GoogleSearch(tile.query.clone())),
-                    icons: None,
-                    desc: "Web Search".to_string(),
-                    name: format!("Search for: {}", tile.query),
-                    name_lc: String::new(),
-                });
-            } else if tile.results.is_empty() && tile.query_lc == "lemon" {
-                tile.results.push(App {
-                    open_command: AppCommand::Display,
-                    desc: "Easter Egg".to_string(),
-                    icons: Some(Handle::from_path(Path::new(
-                        "/Applications/Rustcast.app/Contents/Resources/lemon.png",
-                    ))),
-                    name: "Lemon".to_string(),
-                    name_lc: "".to_string(),
-                });
-            }
             if !tile.query_lc.is_empty() && tile.page == Page::EmojiSearch {
                 tile.results = tile
                     .emoji_apps
@@ -443,20 +507,15 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
                     .collect();
             }
 
-            let new_length = tile.results.len();
-            let max_elem = min(5, new_length);
+            let search = if tile.page == Page::Main {
+                tile.start_search(id)
+            } else {
+                Task::none()
+            };
 
-            if prev_size != new_length && tile.page != Page::ClipboardHistory {
-                Task::batch([
-                    window::resize(
-                        id,
-                        iced::Size {
-                            width: WINDOW_WIDTH,
-                            height: ((max_elem * 55) + 35 + DEFAULT_WINDOW_HEIGHT as usize) as f32,
-                        },
-                    ),
-                    Task::done(Message::ChangeFocus(ArrowKey::Left)),
-                ])
+            let new_length = tile.results.len();
+            let resize = if prev_size != new_length && tile.page != Page::ClipboardHistory {
+                resize_for_results(id, new_length)
             } else if tile.page == Page::ClipboardHistory {
                 Task::batch([
                     window::resize(
@@ -470,11 +529,185 @@ pub fn handle_update(tile: &mut Tile, message: Message) -> Task<Message> {
                 ])
             } else {
                 Task::none()
+            };
+
+            Task::batch([search, resize])
+        }
+
+        Message::ShellOutput(event) => {
+            if let Some(run) = &mut tile.shell_run {
+                run.handle_event(event);
             }
+            Task::none()
         }
+
+        Message::ProviderResults(generation, source, priority, rows, id) => {
+            let prev_size = tile.results.len();
+            if !tile.insert_results(generation, &source, priority, rows) || tile.page != Page::Main
+            {
+                return Task::none();
+            }
+
+            let new_length = tile.results.len();
+            if prev_size != new_length {
+                resize_for_results(id, new_length)
+            } else {
+                Task::none()
+            }
+        }
+
+        Message::ExtensionUpdate(query, name, priority, rows) => {
+            // Updates for a query that has since changed are stale
+            if query != tile.query || tile.page != Page::Main {
+                return Task::none();
+            }
+
+            tile.add_rows(&name, priority, rows);
+            let count = tile.results.len();
+            window::latest().and_then(move |id| resize_for_results(id, count))
+        }
+
+        Message::PushView(view) => {
+            tile.close_shell_output();
+            tile.push_page(Page::View);
+            tile.views.push(ViewState::new(view));
+            tile.focus_id = 0;
+            Task::batch([
+                Task::done(Message::ClearSearchQuery),
+                Task::done(Message::ClearSearchResults),
+                window::latest().and_then(resize_for_view),
+            ])
+        }
+
+        Message::FormInput(index, value) => {
+            if let Some(state) = tile.views.last_mut() {
+                state.set_input(index, value);
+            }
+            Task::none()
+        }
+
+        Message::SubmitForm => {
+            let Some(state) = tile.views.last() else {
+                return Task::none();
+            };
+            if let View::Form { id, extension, .. } = &state.view
+                && let Some(extension) = extension
+            {
+                extensions::submit_form(extension, id, state.form_values());
+            } else if let View::Form { id, .. } = &state.view
+                && let Some(name) = id.strip_prefix(ALIAS_FORM)
+            {
+                let name = name.to_string();
+                let values = state.form_values();
+                let alias = values
+                    .get("alias")
+                    .and_then(|alias| alias.as_str())
+                    .unwrap_or_default();
+                tile.set_alias(&name, alias);
+                save_config(&tile.config);
+                // Past the action panel that the form was opened from, to the results
+                return window::latest().map(|x| x.unwrap()).then(|id| {
+                    Task::done(Message::GoBack(id)).chain(Task::done(Message::GoBack(id)))
+                });
+            } else if let View::Form { id, .. } = &state.view
+                && let Some(path) = id.strip_prefix(ARGUMENTS_FORM)
+                && let Some(script) = scripts::read_script(Path::new(path))
+            {
+                // The script runs as if the arguments were typed after its alias
+                let values = state.form_values();
+                let arguments = (1..=script.arguments.len())
+                    .map(|number| {
+                        let value = values.get(&format!("arg{number}"));
+                        quote(value.and_then(|value| value.as_str()).unwrap_or_default())
+                    })
+                    .collect::<Vec<_>>();
+                let query = format!("{} {}", script.alias, arguments.join(" "));
+                let shelly = Box::new(script.to_shelly());
+                return window::latest().map(|x| x.unwrap()).then(move |id| {
+                    Task::done(Message::GoBack(id))
+                        .chain(Task::done(Message::SearchQueryChanged(query.clone(), id)))
+                        .chain(Task::done(Message::RunFunction(Function::RunShellCommand(
+                            shelly.clone(),
+                        ))))
+                });
+            }
+            window::latest().map(|x| x.unwrap()).map(Message::GoBack)
+        }
+
+        Message::GoBack(id) => go_back(tile, id),
     }
 }
 
+/// Resize the window to fit the results, up to five rows, and scroll to the focused row
+fn resize_for_results(id: window::Id, count: usize) -> Task<Message> {
+    let max_elem = min(5, count);
+    Task::batch([
+        window::resize(
+            id,
+            iced::Size {
+                width: WINDOW_WIDTH,
+                height: ((max_elem * 55) + 35 + DEFAULT_WINDOW_HEIGHT as usize) as f32,
+            },
+        ),
+        Task::done(Message::ChangeFocus(ArrowKey::Left)),
+    ])
+}
+
+/// Go back to the page before the current one, with its query and focus as they were
+fn go_back(tile: &mut Tile, id: window::Id) -> Task<Message> {
+    let Some(state) = tile.back.pop() else {
+        return Task::none();
+    };
+    if tile.page == Page::View {
+        tile.views.pop();
+    }
+    tile.close_shell_output();
+    tile.page = state.page;
+
+    let search = handle_update(tile, Message::SearchQueryChanged(state.query, id));
+    tile.focus_id = state.focus_id;
+    let resize = if tile.page == Page::View {
+        resize_for_view(id)
+    } else {
+        Task::none()
+    };
+    Task::batch([search, resize])
+}
+
+/// Resize the window to the height of a view
+fn resize_for_view(id: window::Id) -> Task<Message> {
+    window::resize(
+        id,
+        iced::Size {
+            width: WINDOW_WIDTH,
+            height: ((7 * 55) + 35 + DEFAULT_WINDOW_HEIGHT as usize) as f32,
+        },
+    )
+}
+
+/// Resize the window for the home screen, or to only the search bar if the home screen is empty
+fn resize_for_home(id: window::Id, count: usize) -> Task<Message> {
+    if count > 0 {
+        return resize_for_results(id, count);
+    }
+    window::resize(
+        id,
+        iced::Size {
+            width: WINDOW_WIDTH,
+            height: DEFAULT_WINDOW_HEIGHT,
+        },
+    )
+}
+
+/// Write the config to the config file, off the main thread
+fn save_config(config: &Config) {
+    let home = std::env::var("HOME").unwrap_or_default();
+    let Ok(config) = toml::to_string(config) else {
+        return;
+    };
+    thread::spawn(move || fs::write(home + "/.config/rustcast/config.toml", config));
+}
+
 fn open_window() -> Task<Message> {
     Task::chain(
         window::open(default_settings())
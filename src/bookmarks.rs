@@ -0,0 +1,301 @@
+//! Bookmarks and history, read from the profile directories of the browsers
+//!
+//! Chromium-family browsers (Chrome, Brave, Edge, Arc) keep their bookmarks in a `Bookmarks` JSON
+//! file and their history in a `History` database, and Firefox keeps both in `places.sqlite`. The
+//! databases are locked while the browser is open, so they are copied to a temporary file and read
+//! from there with a read-only connection, and the browser's own files are never opened by SQLite.
+//! The files are checked for changes in the background, so searching never waits on a copy.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex, Once, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use rusqlite::{Connection, OpenFlags};
+use serde_json::Value;
+
+/// How many history entries are read from each profile, starting with the most visited
+const HISTORY_LIMIT: usize = 2000;
+
+/// How often the browser's files are checked for changes
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The entries of the profiles, which is empty until they were first read
+static INDEX: RwLock<Vec<Bookmark>> = RwLock::new(Vec::new());
+
+/// The profiles to read and whether their history is read too, as of the last config load
+static SETTINGS: Mutex<Option<(Vec<PathBuf>, bool)>> = Mutex::new(None);
+
+/// Where an entry came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Bookmark,
+    History,
+}
+
+/// A bookmark or a visited page
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: String,
+    pub source: Source,
+    title_lc: String,
+    url_lc: String,
+}
+
+impl Bookmark {
+    fn new(title: &str, url: &str, source: Source) -> Bookmark {
+        // Pages without a title are shown as their URL
+        let title = if title.trim().is_empty() {
+            url
+        } else {
+            title.trim()
+        };
+        Bookmark {
+            title: title.to_string(),
+            url: url.to_string(),
+            source,
+            title_lc: title.to_lowercase(),
+            url_lc: url.to_lowercase(),
+        }
+    }
+
+    /// Whether every word of the query is in the title or the URL
+    fn matches(&self, words: &[&str]) -> bool {
+        words
+            .iter()
+            .all(|word| self.title_lc.contains(word) || self.url_lc.contains(word))
+    }
+}
+
+/// Keep the index up to date with the profiles, starting the thread that does it the first time
+///
+/// The entries are read again when one of the browser's files was changed (or added, or removed)
+/// since they were last read
+pub fn watch(profiles: Vec<PathBuf>, history: bool) {
+    if let Ok(mut current) = SETTINGS.lock() {
+        *current = Some((profiles, history));
+    }
+
+    static STARTED: Once = Once::new();
+    STARTED.call_once(|| {
+        thread::spawn(|| {
+            let mut last_read = None;
+            loop {
+                let settings = SETTINGS.lock().ok().and_then(|settings| settings.clone());
+                if let Some((profiles, history)) = settings {
+                    let modified = source_files(&profiles, history)
+                        .into_iter()
+                        .filter_map(|file| {
+                            Some((file.clone(), fs::metadata(&file).ok()?.modified().ok()?))
+                        })
+                        .collect::<Vec<_>>();
+                    let read = (modified, history);
+                    if last_read.as_ref() != Some(&read) {
+                        let entries = read_entries(&read.0, history);
+                        if let Ok(mut index) = INDEX.write() {
+                            *index = entries;
+                        }
+                        last_read = Some(read);
+                    }
+                }
+                thread::sleep(REFRESH_INTERVAL);
+            }
+        });
+    });
+}
+
+/// The entries that have every word of the query in their title or URL, bookmarks first
+pub fn search(query: &str, limit: usize) -> Vec<Bookmark> {
+    let words = query.split_whitespace().collect::<Vec<_>>();
+    INDEX
+        .read()
+        .map(|index| {
+            index
+                .iter()
+                .filter(|entry| entry.matches(&words))
+                .take(limit)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Read the entries of the browser's files
+fn read_entries(files: &[(PathBuf, SystemTime)], history: bool) -> Vec<Bookmark> {
+    let mut entries: Vec<Bookmark> = files
+        .iter()
+        .flat_map(|(file, _)| read_file(file, history))
+        .collect();
+    // Bookmarks are shown before history, and the same page is only shown once
+    entries.sort_by_key(|entry| entry.source != Source::Bookmark);
+    let mut seen = std::collections::HashSet::new();
+    entries.retain(|entry| seen.insert(entry.url.clone()));
+    entries
+}
+
+/// The files that bookmarks and history are read from in the profiles
+///
+/// A path that isn't a profile itself is searched one level down, so that a Firefox `Profiles`
+/// directory can be given instead of a profile with a random name
+fn source_files(profiles: &[PathBuf], history: bool) -> Vec<PathBuf> {
+    let in_profile = |profile: &Path| {
+        let mut files = vec![profile.join("Bookmarks"), profile.join("places.sqlite")];
+        if history {
+            files.push(profile.join("History"));
+        }
+        files
+            .into_iter()
+            .filter(|file| file.is_file())
+            .collect::<Vec<_>>()
+    };
+
+    profiles
+        .iter()
+        .flat_map(|profile| {
+            let files = in_profile(profile);
+            if !files.is_empty() {
+                return files;
+            }
+            fs::read_dir(profile)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .flat_map(|entry| in_profile(&entry.path()))
+                .collect()
+        })
+        .collect()
+}
+
+/// Read the entries of one of the browser's files
+fn read_file(file: &Path, history: bool) -> Vec<Bookmark> {
+    match file.file_name().and_then(|name| name.to_str()) {
+        Some("Bookmarks") => chromium_bookmarks(file),
+        Some("History") => query_database(
+            file,
+            &format!(
+                "SELECT title, url FROM urls WHERE hidden = 0 \
+                 ORDER BY visit_count DESC LIMIT {HISTORY_LIMIT}"
+            ),
+        )
+        .into_iter()
+        .map(|(title, url)| Bookmark::new(&title, &url, Source::History))
+        .collect(),
+        Some("places.sqlite") => firefox_places(file, history),
+        _ => vec![],
+    }
+}
+
+/// Read a Chromium `Bookmarks` file, which is a tree of folders under a few roots
+fn chromium_bookmarks(file: &Path) -> Vec<Bookmark> {
+    fn walk(node: &Value, bookmarks: &mut Vec<Bookmark>) {
+        match node.get("type").and_then(Value::as_str) {
+            Some("url") => {
+                let title = node.get("name").and_then(Value::as_str).unwrap_or("");
+                if let Some(url) = node.get("url").and_then(Value::as_str) {
+                    bookmarks.push(Bookmark::new(title, url, Source::Bookmark));
+                }
+            }
+            _ => {
+                for child in node
+                    .get("children")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    walk(child, bookmarks);
+                }
+            }
+        }
+    }
+
+    let Some(json) = fs::read_to_string(file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+    else {
+        return vec![];
+    };
+
+    let mut bookmarks = vec![];
+    for root in json
+        .get("roots")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|roots| roots.values())
+    {
+        walk(root, &mut bookmarks);
+    }
+    bookmarks
+}
+
+/// Read the bookmarks (and the history) from a Firefox `places.sqlite` database
+fn firefox_places(file: &Path, history: bool) -> Vec<Bookmark> {
+    let bookmarks = query_database(
+        file,
+        "SELECT b.title, p.url FROM moz_bookmarks b JOIN moz_places p ON b.fk = p.id \
+         WHERE b.type = 1",
+    )
+    .into_iter()
+    .map(|(title, url)| Bookmark::new(&title, &url, Source::Bookmark));
+
+    let visited = if history {
+        query_database(
+            file,
+            &format!(
+                "SELECT title, url FROM moz_places WHERE hidden = 0 AND visit_count > 0 \
+                 ORDER BY frecency DESC LIMIT {HISTORY_LIMIT}"
+            ),
+        )
+    } else {
+        vec![]
+    }
+    .into_iter()
+    .map(|(title, url)| Bookmark::new(&title, &url, Source::History));
+
+    bookmarks.chain(visited).collect()
+}
+
+/// Run a query that selects a title and a URL on a copy of a browser's database
+fn query_database(file: &Path, sql: &str) -> Vec<(String, String)> {
+    // Every copy gets its own name, so that two reads of the same file can't clash
+    static COPIES: AtomicUsize = AtomicUsize::new(0);
+    let copy = std::env::temp_dir().join(format!(
+        "rustcast-{}-{}.sqlite",
+        std::process::id(),
+        COPIES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let sidecar = |path: &Path, suffix: &str| PathBuf::from(format!("{}-{suffix}", path.display()));
+
+    let rows = fs::copy(file, &copy).ok().and_then(|_| {
+        // Recent changes can still be in the write-ahead log next to the database
+        fs::copy(sidecar(file, "wal"), sidecar(&copy, "wal")).ok();
+        read_rows(&copy, sql).ok()
+    });
+
+    fs::remove_file(&copy).ok();
+    for suffix in ["wal", "shm"] {
+        fs::remove_file(sidecar(&copy, suffix)).ok();
+    }
+    rows.unwrap_or_default()
+}
+
+/// The titles and URLs that a query selects from a database, which is only opened for reading
+fn read_rows(file: &Path, sql: &str) -> rusqlite::Result<Vec<(String, String)>> {
+    let connection = Connection::open_with_flags(
+        file,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    let mut statement = connection.prepare(sql)?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+        ))
+    })?;
+    Ok(rows.flatten().filter(|(_, url)| !url.is_empty()).collect())
+}
@@ -5,6 +5,10 @@
 /// - ln(x)
 /// - log(x) (base 10)
 /// - log(base, x)
+/// - units on numbers, with dimensional analysis (see [`crate::unit_conversion`])
+/// - converting the result with `to`/`in`/`as` followed by units
+/// - mixed units, summed on input (`1h 30min`) and split on output (`to ft+in`, `to h:min:s`)
+/// - currency symbols before or after amounts (`$5`, `20€`)
 ///
 /// Examples:
 ///   "2 + 3*4"        => 14
@@ -13,6 +17,12 @@
 ///   "ln(2.7182818)"  => ~1
 ///   "log(100)"       => 2
 ///   "log(2, 8)"      => 3
+///   "5 ft + 3 in to cm" => 160.02 cm
+///   "60 km / 1.5 h"  => 40 km/h
+///   "5ft 11in to cm" => 180.34 cm
+///   "100 min to h:min" => 1:40
+///   "$20 to eur"     => 18.52 EUR (with the rates file)
+use crate::unit_conversion::{Quantity, UnitCategory, UnitDef, Units, find_unit};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
@@ -30,6 +40,20 @@ pub enum Expr {
         name: String,
         args: Vec<Expr>,
     },
+    Quantity {
+        value: Box<Expr>,
+        units: Units,
+    },
+    Convert {
+        expr: Box<Expr>,
+        target: Units,
+    },
+    /// Converting into several units at once, like `to ft+in` or `to h:min:s` (a clock)
+    ConvertMixed {
+        expr: Box<Expr>,
+        units: Vec<&'static UnitDef>,
+        clock: bool,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -48,59 +72,113 @@ pub enum BinOp {
 }
 
 impl Expr {
+    /// Evaluate an expression that has no units in it
     pub fn eval(&self) -> Option<f64> {
+        self.eval_quantity().ok()?.to_scalar()
+    }
+
+    /// Evaluate an expression, keeping track of the units of every value in it
+    pub fn eval_quantity(&self) -> Result<Quantity, String> {
         use BinOp::*;
         use UnaryOp::*;
         match self {
-            Expr::Number(x) => Some(*x),
+            Expr::Number(x) => Ok(Quantity::scalar(*x)),
 
             Expr::Unary { op, rhs } => {
-                let v = rhs.eval()?;
-                Some(match op {
-                    Plus => v,
-                    Minus => -v,
-                })
+                let mut v = rhs.eval_quantity()?;
+                if *op == Minus {
+                    v.value = -v.value;
+                }
+                Ok(v)
             }
 
             Expr::Binary { op, lhs, rhs } => {
-                let a = lhs.eval()?;
-                let b = rhs.eval()?;
+                let a = lhs.eval_quantity()?;
+                let b = rhs.eval_quantity()?;
                 match op {
-                    Add => Some(a + b),
-                    Sub => Some(a - b),
-                    Mul => Some(a * b),
-                    Div => Some(a / b),
-                    Pow => Some(a.powf(b)),
+                    Add => a.add(b),
+                    Sub => a.sub(b),
+                    Mul => Ok(a.mul(b)),
+                    Div => Ok(a.div(b)),
+                    Pow => {
+                        let exp = b
+                            .to_scalar()
+                            .ok_or("The exponent of a power can't have units")?;
+                        if let Some(base) = a.to_scalar() {
+                            Ok(Quantity::scalar(base.powf(exp)))
+                        } else if exp.fract() == 0.0 {
+                            Ok(a.powi(exp as i32))
+                        } else {
+                            Err("Units can only be raised to whole powers".to_string())
+                        }
+                    }
                 }
             }
 
             Expr::Func { name, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| {
+                        arg.eval_quantity()?
+                            .to_scalar()
+                            .ok_or(format!("{name} can't be used on values with units"))
+                    })
+                    .collect::<Result<Vec<f64>, String>>()?;
                 let name = name.as_str();
-                match name {
+                let value = match name {
                     "ln" => {
                         if args.len() != 1 {
-                            return None;
+                            return Err("ln takes 1 argument".to_string());
                         }
-                        Some(args[0].eval()?.ln())
+                        args[0].ln()
                     }
                     "log" => match args.len() {
-                        1 => Some(args[0].eval()?.log10()),
-                        2 => {
-                            let base = args[0].eval()?;
-                            let x = args[1].eval()?;
-                            Some(x.log(base))
-                        }
-                        _ => None,
+                        1 => args[0].log10(),
+                        2 => args[1].log(args[0]),
+                        _ => return Err("log takes 1 or 2 arguments".to_string()),
                     },
-                    _ => None,
-                }
+                    _ => return Err(format!("Unknown function: {name}")),
+                };
+                Ok(Quantity::scalar(value))
             }
+
+            Expr::Quantity { value, units } => Ok(value.eval_quantity()?.mul(Quantity {
+                value: 1.0,
+                units: units.clone(),
+            })),
+
+            Expr::Convert { expr, target } => expr.eval_quantity()?.convert_to(target),
+
+            // The largest unit is the value of the whole thing
+            Expr::ConvertMixed { expr, units, .. } => expr
+                .eval_quantity()?
+                .convert_to(&[(units.first().ok_or("No units to convert to")?, 1)]),
+        }
+    }
+
+    /// Whether any part of the expression has units attached to it
+    pub fn has_units(&self) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Unary { rhs, .. } => rhs.has_units(),
+            Expr::Binary { lhs, rhs, .. } => lhs.has_units() || rhs.has_units(),
+            Expr::Func { args, .. } => args.iter().any(|arg| arg.has_units()),
+            Expr::Quantity { .. } | Expr::Convert { .. } | Expr::ConvertMixed { .. } => true,
         }
     }
 
     pub fn from_str(s: &str) -> Result<Expr, String> {
         let mut p = Parser::new(s);
-        let expr = p.parse_expr()?;
+        let mut expr = p.parse_expr()?;
+
+        // `<expr> to <units>`, `<expr> in <units>` or just `<expr> <units>`
+        if let Token::Ident(word) = &p.cur {
+            if matches!(word.as_str(), "to" | "in" | "as") && p.peek_is_ident() {
+                p.bump()?;
+            }
+            expr = p.parse_target(expr)?;
+        }
+
         p.expect(Token::End)?;
         Ok(expr)
     }
@@ -120,9 +198,11 @@ enum Token {
     LParen,
     RParen,
     Comma,
+    Colon,
     End,
 }
 
+#[derive(Clone)]
 struct Lexer<'a> {
     input: &'a str,
     i: usize,
@@ -190,11 +270,15 @@ impl<'a> Lexer<'a> {
                 self.bump_char();
                 Token::Comma
             }
+            ':' => {
+                self.bump_char();
+                Token::Colon
+            }
             _ => {
                 // number or identifier
                 if c.is_ascii_digit() || c == '.' {
                     return self.lex_number();
-                } else if c.is_ascii_alphabetic() || c == '_' {
+                } else if c.is_alphabetic() || c == '_' || is_currency_symbol(c) {
                     return self.lex_ident();
                 } else {
                     return Err(format!("Unexpected character: {c}"));
@@ -204,6 +288,16 @@ impl<'a> Lexer<'a> {
         Ok(tok)
     }
 
+    /// Whether the `e` at the cursor starts an exponent (like `1e-3`) rather than a unit (`5eV`)
+    fn exponent_follows(&self) -> bool {
+        let mut rest = self.input[self.i..].chars().skip(1);
+        match rest.next() {
+            Some('+' | '-') => rest.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
     fn lex_number(&mut self) -> Result<Token, String> {
         // Simple float lexer: digits/./e/E/+/- in exponent
         let start = self.i;
@@ -214,7 +308,7 @@ impl<'a> Lexer<'a> {
                 self.bump_char();
                 continue;
             }
-            if (c == 'e' || c == 'E') && !seen_e {
+            if (c == 'e' || c == 'E') && !seen_e && self.exponent_follows() {
                 seen_e = true;
                 self.bump_char();
                 // optional sign after exponent
@@ -235,8 +329,13 @@ impl<'a> Lexer<'a> {
 
     fn lex_ident(&mut self) -> Result<Token, String> {
         let start = self.i;
+        // Currency symbols are units on their own, but can also end a name like `C$` or `HK$`
+        if self.peek_char().is_some_and(is_currency_symbol) {
+            self.bump_char();
+            return Ok(Token::Ident(self.input[start..self.i].to_string()));
+        }
         while let Some(c) = self.peek_char() {
-            if c.is_ascii_alphanumeric() || c == '_' {
+            if c.is_alphanumeric() || c == '_' || is_currency_symbol(c) {
                 self.bump_char();
             } else {
                 break;
@@ -246,6 +345,11 @@ impl<'a> Lexer<'a> {
     }
 }
 
+/// Whether a character is a currency symbol like `$` or `€`
+fn is_currency_symbol(c: char) -> bool {
+    matches!(c, '$' | '£' | '¥' | '฿' | '\u{20A0}'..='\u{20CF}')
+}
+
 /* ---------------- Parser ---------------- */
 
 struct Parser<'a> {
@@ -265,6 +369,11 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
+    /// Whether the token after the current one is an identifier
+    fn peek_is_ident(&self) -> bool {
+        matches!(self.lex.clone().next_token(), Ok(Token::Ident(_)))
+    }
+
     fn expect(&mut self, t: Token) -> Result<(), String> {
         if self.cur == t {
             self.bump()
@@ -346,7 +455,138 @@ impl<'a> Parser<'a> {
                     rhs: Box::new(self.parse_unary()?),
                 })
             }
-            _ => self.parse_primary(),
+            _ => self.parse_quantity(),
+        }
+    }
+
+    fn parse_quantity(&mut self) -> Result<Expr, String> {
+        // quantity = primary (unit_power (number unit_power)*)?
+        let value = self.parse_primary()?;
+        // amounts that already have their unit in front, like `$5`
+        if matches!(value, Expr::Quantity { .. }) {
+            return Ok(value);
+        }
+        let Some(units) = self.parse_unit_suffix()? else {
+            return Ok(value);
+        };
+        let mut quantity = Expr::Quantity {
+            value: Box::new(value),
+            units,
+        };
+
+        // Mixed units like `5ft 11in` or `1h 30min` are added together
+        while let Token::Number(n) = self.cur {
+            self.bump()?;
+            let units = self
+                .parse_unit_suffix()?
+                .ok_or(format!("Expected a unit after {n}"))?;
+            quantity = Expr::Binary {
+                op: BinOp::Add,
+                lhs: Box::new(quantity),
+                rhs: Box::new(Expr::Quantity {
+                    value: Box::new(Expr::Number(n)),
+                    units,
+                }),
+            };
+        }
+        Ok(quantity)
+    }
+
+    /// Parses the unit after a value, if there is one
+    fn parse_unit_suffix(&mut self) -> Result<Option<Units>, String> {
+        let Token::Ident(name) = &self.cur else {
+            return Ok(None);
+        };
+        if find_unit(name).is_none() {
+            return Ok(None);
+        }
+        let name = name.clone();
+        self.bump()?;
+        Ok(Some(vec![self.parse_unit_power(&name)?]))
+    }
+
+    fn parse_target(&mut self, expr: Expr) -> Result<Expr, String> {
+        // target = units | unit ('+' unit)+ | unit (':' unit)+
+        let units = self.parse_units()?;
+        let separator = match self.cur {
+            Token::Plus | Token::Colon => self.cur.clone(),
+            _ => {
+                return Ok(Expr::Convert {
+                    expr: Box::new(expr),
+                    target: units,
+                });
+            }
+        };
+
+        let mut mixed = Vec::new();
+        let mut units = units;
+        loop {
+            match units.as_slice() {
+                [(unit, 1)] => mixed.push(*unit),
+                _ => return Err("Mixed units can't be raised to powers".to_string()),
+            }
+            if self.cur != separator {
+                break;
+            }
+            self.bump()?;
+            let Token::Ident(name) = &self.cur else {
+                return Err(format!("Expected a unit, found {:?}", self.cur));
+            };
+            let name = name.clone();
+            self.bump()?;
+            units = vec![self.parse_unit_power(&name)?];
+        }
+
+        Ok(Expr::ConvertMixed {
+            expr: Box::new(expr),
+            units: mixed,
+            clock: separator == Token::Colon,
+        })
+    }
+
+    fn parse_units(&mut self) -> Result<Units, String> {
+        // units = unit_power (('*'|'/') unit_power)*
+        let mut units = Vec::new();
+        let mut sign = 1;
+        loop {
+            let Token::Ident(name) = &self.cur else {
+                return Err(format!("Expected a unit, found {:?}", self.cur));
+            };
+            let name = name.clone();
+            self.bump()?;
+            let (unit, exp) = self.parse_unit_power(&name)?;
+            units.push((unit, exp * sign));
+
+            sign = match self.cur {
+                Token::Star => 1,
+                Token::Slash => -1,
+                _ => break,
+            };
+            self.bump()?;
+        }
+        Ok(units)
+    }
+
+    fn parse_unit_power(&mut self, name: &str) -> Result<(&'static UnitDef, i32), String> {
+        // unit_power = unit ('^' '-'? number)?
+        let unit = find_unit(name).ok_or(format!("Unknown unit: {name}"))?;
+        if self.cur != Token::Caret {
+            return Ok((unit, 1));
+        }
+
+        self.bump()?;
+        let sign = if self.cur == Token::Minus {
+            self.bump()?;
+            -1
+        } else {
+            1
+        };
+        match self.cur {
+            Token::Number(n) if n.fract() == 0.0 => {
+                self.bump()?;
+                Ok((unit, sign * n as i32))
+            }
+            _ => Err(format!("Expected a whole power for {name}")),
         }
     }
 
@@ -366,6 +606,24 @@ impl<'a> Parser<'a> {
             Token::Ident(name) => {
                 let name = name.clone();
                 self.bump()?;
+                // currencies can come before the amount, like `$5`
+                if let Token::Number(n) = self.cur
+                    && let Some(unit) = find_unit(&name)
+                    && unit.category == UnitCategory::Currency
+                {
+                    self.bump()?;
+                    return Ok(Expr::Quantity {
+                        value: Box::new(Expr::Number(n)),
+                        units: vec![(unit, 1)],
+                    });
+                }
+                // a unit on its own, like the `s` in `m / s`
+                if self.cur != Token::LParen {
+                    return Ok(Expr::Quantity {
+                        value: Box::new(Expr::Number(1.0)),
+                        units: vec![self.parse_unit_power(&name)?],
+                    });
+                }
                 // function call must be ident '(' ...
                 self.expect(Token::LParen)?;
                 let mut args = Vec::new();
@@ -6,27 +6,48 @@ use arboard::Clipboard;
 use objc2_app_kit::NSWorkspace;
 use objc2_foundation::NSURL;
 
-use crate::{calculator::Expr, clipboard::ClipBoardContentType, config::Config};
+use crate::{
+    calculator::Expr,
+    clipboard::ClipBoardContentType,
+    config::Shelly,
+    extensions, shell,
+    utils::{open_url, parse_url, percent_encode, private_browser, search_url, text_after_alias},
+};
 
 /// The different functions that rustcast can perform
 #[derive(Debug, Clone, PartialEq)]
 pub enum Function {
     OpenApp(String),
-    RunShellCommand(String, String),
+    /// Open a file or folder with its default app
+    OpenFile(String),
+    RunShellCommand(Box<Shelly>),
     OpenWebsite(String),
     RandomVar(i32), // Easter egg function
     CopyToClipboard(ClipBoardContentType),
-    GoogleSearch(String),
+    /// Search the web, with a search URL and the search arguments
+    WebSearch(String, String),
+    /// Open a quicklink, with its URL, its alias and the app to open it with
+    OpenQuicklink(String, String, Option<String>),
+    /// Run an item of an extension, with the extension's name and the item's id
+    RunExtensionItem(String, String),
     Calculate(Expr),
+    /// Show an app or a file in Finder
+    RevealInFinder(String),
+    /// Open a file with an app, by their paths
+    OpenWith(String, String),
+    /// Quit an app if it is running, by its path
+    QuitApp(String),
+    /// Open a URL in a private window of the default browser
+    OpenPrivateWindow(String),
     OpenPrefPane,
     Quit,
 }
 
 impl Function {
     /// Run the command
-    pub fn execute(&self, config: &Config, query: &str) {
+    pub fn execute(&self, query: &str, selection: Option<&str>) {
         match self {
-            Function::OpenApp(path) => {
+            Function::OpenApp(path) | Function::OpenFile(path) => {
                 let path = path.to_owned();
                 thread::spawn(move || {
                     NSWorkspace::new().openURL(&NSURL::fileURLWithPath(
@@ -34,16 +55,7 @@ impl Function {
                     ));
                 });
             }
-            Function::RunShellCommand(command, alias) => {
-                let query = query.to_string();
-                let final_command =
-                    format!(r#"{} {}"#, command, query.strip_prefix(alias).unwrap_or(""));
-                Command::new("sh")
-                    .arg("-c")
-                    .arg(final_command.trim())
-                    .spawn()
-                    .ok();
-            }
+            Function::RunShellCommand(shell) => shell::run(shell, query),
             Function::RandomVar(var) => {
                 Clipboard::new()
                     .unwrap()
@@ -51,36 +63,44 @@ impl Function {
                     .unwrap_or(());
             }
 
-            Function::GoogleSearch(query_string) => {
-                let query_args = query_string.replace(" ", "+");
-                let query = config.search_url.replace("%s", &query_args);
-                let query = query.strip_suffix("?").unwrap_or(&query).to_string();
-                thread::spawn(move || {
-                    NSWorkspace::new().openURL(
-                        &NSURL::URLWithString_relativeToURL(
-                            &objc2_foundation::NSString::from_str(&query),
-                            None,
-                        )
-                        .unwrap(),
-                    );
-                });
+            Function::WebSearch(url, query_string) => {
+                open_url(&search_url(url, query_string));
             }
 
+            Function::OpenQuicklink(url, alias, open_with) => {
+                let args = text_after_alias(query, alias);
+                let clipboard = Clipboard::new()
+                    .and_then(|mut clipboard| clipboard.get_text())
+                    .unwrap_or_default();
+                let url = fill_placeholders(
+                    url,
+                    &[
+                        ("{query}", args),
+                        ("{clipboard}", &clipboard),
+                        ("{selection}", selection.unwrap_or("")),
+                    ],
+                );
+
+                match open_with {
+                    Some(app) => {
+                        Command::new("open")
+                            .arg("-a")
+                            .arg(app)
+                            .arg(url)
+                            .spawn()
+                            .ok();
+                    }
+                    None => open_url(&url),
+                }
+            }
+
+            Function::RunExtensionItem(extension, id) => extensions::run_item(extension, id),
+
             Function::OpenWebsite(url) => {
-                let open = if url.starts_with("http") {
-                    url.to_owned()
-                } else {
-                    format!("https://{}", url)
-                };
-                thread::spawn(move || {
-                    NSWorkspace::new().openURL(
-                        &NSURL::URLWithString_relativeToURL(
-                            &objc2_foundation::NSString::from_str(&open),
-                            None,
-                        )
-                        .unwrap(),
-                    );
-                });
+                let url = parse_url(url, &[])
+                    .map(|url| url.to_string())
+                    .unwrap_or(url.to_owned());
+                open_url(&url);
             }
 
             Function::Calculate(expr) => {
@@ -99,6 +119,37 @@ impl Function {
                 }
             },
 
+            Function::RevealInFinder(path) => {
+                Command::new("open").arg("-R").arg(path).spawn().ok();
+            }
+
+            Function::OpenWith(path, app) => {
+                Command::new("open")
+                    .arg("-a")
+                    .arg(app)
+                    .arg(path)
+                    .spawn()
+                    .ok();
+            }
+
+            Function::QuitApp(path) => {
+                let app = path.replace('\\', "\\\\").replace('"', "\\\"");
+                let script = format!(
+                    "if application \"{app}\" is running then tell application \"{app}\" to quit"
+                );
+                Command::new("osascript").arg("-e").arg(script).spawn().ok();
+            }
+
+            Function::OpenPrivateWindow(url) => match private_browser() {
+                Some((browser, flag)) => {
+                    Command::new("open")
+                        .args(["-na", browser, "--args", flag, url])
+                        .spawn()
+                        .ok();
+                }
+                None => open_url(url),
+            },
+
             Function::OpenPrefPane => {
                 thread::spawn(move || {
                     NSWorkspace::new().openURL(&NSURL::fileURLWithPath(
@@ -113,3 +164,12 @@ impl Function {
         }
     }
 }
+
+/// Replace each placeholder in the URL with its percent-encoded value
+fn fill_placeholders(url: &str, placeholders: &[(&str, &str)]) -> String {
+    placeholders
+        .iter()
+        .fold(url.to_string(), |url, (placeholder, value)| {
+            url.replace(placeholder, &percent_encode(value))
+        })
+}
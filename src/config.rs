@@ -1,13 +1,15 @@
 //! This is the config file type definitions for rustcast
-use std::{path::Path, sync::Arc};
+use std::{collections::BTreeMap, sync::Arc};
 
-use iced::{Font, font::Family, theme::Custom, widget::image::Handle};
+use iced::{Font, font::Family, theme::Custom};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app::apps::{App, AppCommand},
+    calculator::Expr,
     commands::Function,
-    utils::handle_from_icns,
+    unit_conversion::{UnitCategory, UnitDef, category_of, intern, intern_aliases},
+    utils::handle_from_path,
 };
 
 /// The main config struct (effectively the config file's "schema")
@@ -20,9 +22,23 @@ pub struct Config {
     pub theme: Theme,
     pub placeholder: String,
     pub search_url: String,
+    pub intranet_hosts: Vec<String>,
+    pub search_engines: Vec<SearchEngine>,
+    pub default_search_engine: Option<String>,
     pub haptic_feedback: bool,
     pub show_trayicon: bool,
     pub shells: Vec<Shelly>,
+    pub quicklinks: Vec<Quicklink>,
+    pub units: Vec<CustomUnit>,
+    pub world_clock: Vec<String>,
+    pub browser_profiles: Vec<String>,
+    pub browser_history: bool,
+    pub extensions: Vec<ExtensionConfig>,
+    pub file_search: FileSearch,
+    pub recent_files: RecentFiles,
+    pub favourites: Vec<String>,
+    pub aliases: BTreeMap<String, String>,
+    pub home: Home,
 }
 
 impl Default for Config {
@@ -35,9 +51,31 @@ impl Default for Config {
             theme: Theme::default(),
             placeholder: String::from("Time to be productive!"),
             search_url: "https://google.com/search?q=%s".to_string(),
+            intranet_hosts: vec![],
+            search_engines: SearchEngine::builtin(),
+            default_search_engine: None,
             haptic_feedback: false,
             show_trayicon: true,
             shells: vec![],
+            quicklinks: vec![],
+            units: vec![],
+            world_clock: vec![],
+            browser_profiles: [
+                "~/Library/Application Support/Google/Chrome/Default",
+                "~/Library/Application Support/BraveSoftware/Brave-Browser/Default",
+                "~/Library/Application Support/Microsoft Edge/Default",
+                "~/Library/Application Support/Arc/User Data/Default",
+                "~/Library/Application Support/Firefox/Profiles",
+            ]
+            .map(String::from)
+            .to_vec(),
+            browser_history: true,
+            extensions: vec![],
+            file_search: FileSearch::default(),
+            recent_files: RecentFiles::default(),
+            favourites: vec![],
+            aliases: BTreeMap::new(),
+            home: Home::default(),
         }
     }
 }
@@ -160,34 +198,152 @@ impl Default for Buffer {
     }
 }
 
-/// Command is the command it will run when the button is clicked
+/// The settings for searching files
+///
+/// - roots are the folders whose files can be searched
+/// - exclude are globs of the paths to leave out, which are matched against the name of a file,
+///   or against its path in the root if the glob has a `/` in it (like `.gitignore` files)
+/// - prefix is what the query has to start with to search files, like `f report`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FileSearch {
+    pub roots: Vec<String>,
+    pub exclude: Vec<String>,
+    pub prefix: String,
+}
+
+impl Default for FileSearch {
+    fn default() -> Self {
+        FileSearch {
+            roots: ["~/Desktop", "~/Documents", "~/Downloads"]
+                .map(String::from)
+                .to_vec(),
+            exclude: ["node_modules", "target", "*.photoslibrary"]
+                .map(String::from)
+                .to_vec(),
+            prefix: "f".to_string(),
+        }
+    }
+}
+
+/// The settings for the recently used files
+///
+/// - enabled is whether they are shown, which is on the home screen, when the query starts with
+///   the prefix, and above the other files of a file search
+/// - prefix is what the query has to start with to only search the recent files, like
+///   `recent report`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RecentFiles {
+    pub enabled: bool,
+    pub prefix: String,
+}
+
+impl Default for RecentFiles {
+    fn default() -> Self {
+        RecentFiles {
+            enabled: true,
+            prefix: "recent".to_string(),
+        }
+    }
+}
+
+/// The settings for the home screen, which is shown when the query is empty
+///
+/// - sections are the sections that are shown, in order ([`HomeSection`]), and with none of them
+///   only the search bar is shown
+/// - rows is how many rows each section has at most
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Home {
+    pub sections: Vec<HomeSection>,
+    pub rows: usize,
+}
+
+impl Default for Home {
+    fn default() -> Self {
+        Home {
+            sections: vec![
+                HomeSection::Favourites,
+                HomeSection::Recent,
+                HomeSection::Files,
+                HomeSection::Clipboard,
+                HomeSection::Toggles,
+            ],
+            rows: 3,
+        }
+    }
+}
+
+/// A section of the home screen
+///
+/// - Favourites are the results named in `favourites`, which are also pinned above the other
+///   results
+/// - Recent are the apps and commands that were launched last
+/// - Files are the recently used files, if they are enabled
+/// - Clipboard are the last few clipboard entries
+/// - Toggles turn settings like the tray icon on and off
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HomeSection {
+    Favourites,
+    Recent,
+    Files,
+    Clipboard,
+    Toggles,
+}
+
+/// Command is the command it will run when the button is clicked, which can have `{args}` (the
+/// words after the alias), `{arg1}`, `{arg2}`, etc, `{query}` (all the text after the alias) and
+/// `{clipboard}` in it. These are quoted, and without any of them the words are added to the end
 /// Icon_path is the path to an icon, but this is optional
 /// Alias is the text that is used to call this command / search for it
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Cwd is the directory to run the command in, and env the environment variables to set
+/// Shell is the shell that runs the command, which is `sh` by default
+/// Timeout is how many seconds the command can run for before it is stopped
+/// Terminal is the name of a terminal app (like "Terminal" or "iTerm") to run the command in
+/// Output is what is done with what the command prints ([`OutputMode`])
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Shelly {
-    command: String,
-    icon_path: Option<String>,
-    alias: String,
-    alias_lc: String,
+    pub command: String,
+    pub icon_path: Option<String>,
+    pub alias: String,
+    pub alias_lc: String,
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    pub shell: Option<String>,
+    pub timeout: Option<u64>,
+    pub terminal: Option<String>,
+    #[serde(default)]
+    pub output: OutputMode,
+}
+
+/// What is done with the output of a shell command
+///
+/// - Silent ignores it
+/// - Notify shows how the command exited and its last line in a notification
+/// - Inline shows it in rustcast as the command runs, where it can be cancelled with escape
+/// - Copy copies it to the clipboard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    #[default]
+    Silent,
+    Notify,
+    Inline,
+    Copy,
 }
 
 impl Shelly {
     /// Converts the shelly struct to an app so that it can be added to the app list
     pub fn to_app(&self) -> App {
         let self_clone = self.clone();
-        let icon = self_clone.icon_path.and_then(|x| {
-            let x = x.replace("~", &std::env::var("HOME").unwrap());
-            if x.ends_with(".icns") {
-                handle_from_icns(Path::new(&x))
-            } else {
-                Some(Handle::from_path(Path::new(&x)))
-            }
-        });
+        let icon = self_clone.icon_path.as_deref().and_then(handle_from_path);
         App {
-            open_command: AppCommand::Function(Function::RunShellCommand(
-                self_clone.command,
-                self_clone.alias_lc.clone(),
-            )),
+            open_command: AppCommand::Function(Function::RunShellCommand(Box::new(
+                self_clone.clone(),
+            ))),
             desc: "Shell Command".to_string(),
             icons: icon,
             name: self_clone.alias,
@@ -195,3 +351,148 @@ impl Shelly {
         }
     }
 }
+
+/// A link that can have the search arguments and more filled into it, like `jira PROJ-123`
+///
+/// - Name is what the link is shown as
+/// - Alias is the text that is used to call this link / search for it
+/// - Url is the link, which can have `{query}` (the text after the alias), `{clipboard}` and
+///   `{selection}` (the text selected in the app that was open before rustcast) in it
+/// - Icon_path is the path to an icon, but this is optional
+/// - Open_with is the name of the app to open the link with, like "Safari", instead of the
+///   default browser
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Quicklink {
+    pub name: String,
+    pub alias: String,
+    pub url: String,
+    pub icon_path: Option<String>,
+    pub open_with: Option<String>,
+}
+
+impl Quicklink {
+    /// Converts the quicklink to an app so that it can be added to the app list
+    pub fn to_app(&self) -> App {
+        App {
+            open_command: AppCommand::Function(Function::OpenQuicklink(
+                self.url.clone(),
+                self.alias.to_lowercase(),
+                self.open_with.clone(),
+            )),
+            desc: "Quicklink".to_string(),
+            icons: self.icon_path.as_deref().and_then(handle_from_path),
+            name: self.name.clone(),
+            name_lc: self.alias.to_lowercase(),
+        }
+    }
+}
+
+/// A search engine that is used when the query starts with its keyword, like `gh rustcast`
+///
+/// - Keyword is the first word of the query that picks this engine
+/// - Name is what the engine is shown as
+/// - Url is the search URL, with a %s to mark where the search arguments will go
+/// - Icon_path is the path to an icon, but this is optional
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SearchEngine {
+    pub keyword: String,
+    pub name: String,
+    pub url: String,
+    pub icon_path: Option<String>,
+}
+
+impl SearchEngine {
+    /// The engines that are there if the config doesn't set any
+    fn builtin() -> Vec<SearchEngine> {
+        [
+            ("gh", "GitHub", "https://github.com/search?q=%s"),
+            ("ddg", "DuckDuckGo", "https://duckduckgo.com/?q=%s"),
+            ("crates", "crates.io", "https://crates.io/search?q=%s"),
+            (
+                "docs.rs",
+                "docs.rs",
+                "https://docs.rs/releases/search?query=%s",
+            ),
+        ]
+        .into_iter()
+        .map(|(keyword, name, url)| SearchEngine {
+            keyword: keyword.to_string(),
+            name: name.to_string(),
+            url: url.to_string(),
+            icon_path: None,
+        })
+        .collect()
+    }
+}
+
+/// An extension, which is a process that rustcast talks to over its stdin and stdout (see
+/// `EXTENSIONS.md`)
+///
+/// - Name is what the extension's rows are described as, and must be unique
+/// - Command is run with `sh -c`, in the cwd if there is one
+/// - Timeout is how many milliseconds the extension has to answer, 1000 if not set
+/// - Keyword is a word that the query has to start with for the extension to be searched, or
+///   every query is sent to it
+/// - Priority is where its rows are ranked (the apps are at 50), 60 if not set
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ExtensionConfig {
+    pub name: String,
+    pub command: String,
+    pub cwd: Option<String>,
+    pub timeout: Option<u64>,
+    pub keyword: Option<String>,
+    pub priority: Option<u8>,
+}
+
+/// A unit of the user's own, that can be converted to and from like the builtin ones
+///
+/// - Name is what the unit is shown as, and can be typed as
+/// - Aliases are the other names it can be typed as
+/// - Category is one of the builtin categories (like "length"), or the name of a new one
+/// - Scale and offset define the unit relative to the base unit of its category
+/// - Equals defines the unit relative to another one instead, like "1.75 in"
+///
+/// A unit of a new category with no scale becomes the base unit of that category
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CustomUnit {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub category: Option<String>,
+    pub scale: Option<f64>,
+    #[serde(default)]
+    pub offset: f64,
+    pub equals: Option<String>,
+}
+
+impl CustomUnit {
+    /// Converts the custom unit into a unit definition so that it can be added to the unit table
+    pub fn to_unit_def(&self) -> Result<UnitDef, String> {
+        let category = self.category.as_deref().map(UnitCategory::from_name);
+
+        let (category, scale) = match (&self.equals, self.scale) {
+            (Some(equals), _) => {
+                let quantity = Expr::from_str(equals)?.eval_quantity()?;
+                let category = category
+                    .or_else(|| category_of(&quantity))
+                    .ok_or(format!("\"{equals}\" isn't in any category"))?;
+                let scale = quantity
+                    .base_value(category)
+                    .ok_or(format!("\"{equals}\" isn't a {category:?} unit"))?;
+                (category, scale)
+            }
+            (None, scale) => (
+                category.ok_or("Either a category or what the unit equals is needed")?,
+                scale.unwrap_or(1.0),
+            ),
+        };
+
+        Ok(UnitDef {
+            name: intern(&self.name),
+            aliases: intern_aliases(&self.aliases.iter().map(String::as_str).collect::<Vec<_>>()),
+            category,
+            scale,
+            offset: self.offset,
+        })
+    }
+}
@@ -0,0 +1,160 @@
+//! Exchange rates for currency conversion, read from a file in the rustcast config directory
+//!
+//! Nothing is downloaded: drop an export of the ECB reference rates into
+//! `~/.config/rustcast/rates.xml` (the `eurofxref-daily.xml` file) or `~/.config/rustcast/rates.json`
+//! (`{"base": "EUR", "date": "2024-05-03", "rates": {"USD": 1.0762, ...}}`) and it is picked up
+//! the next time the config is loaded.
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+
+/// After how many days the rates are flagged as outdated in the results
+pub const STALE_AFTER_DAYS: i64 = 7;
+
+/// The symbols and names that a currency can be written as, besides its ISO code
+pub const CURRENCY_ALIASES: &[(&str, &[&str])] = &[
+    ("EUR", &["€", "euro", "euros"]),
+    ("USD", &["$", "US$", "dollar", "dollars"]),
+    ("GBP", &["£", "pound", "pounds"]),
+    ("JPY", &["¥", "yen"]),
+    ("CNY", &["CN¥", "yuan", "rmb"]),
+    ("INR", &["₹", "rupee", "rupees"]),
+    ("KRW", &["₩", "won"]),
+    ("CHF", &["franc", "francs"]),
+    ("CAD", &["C$"]),
+    ("AUD", &["A$"]),
+    ("NZD", &["NZ$"]),
+    ("HKD", &["HK$"]),
+    ("SGD", &["S$"]),
+    ("BRL", &["R$", "real", "reais"]),
+    ("MXN", &["MX$"]),
+    ("TRY", &["₺", "lira"]),
+    ("ILS", &["₪", "shekel", "shekels"]),
+    ("PHP", &["₱", "peso", "pesos"]),
+    ("THB", &["฿", "baht"]),
+    ("PLN", &["zł", "zloty"]),
+    ("UAH", &["₴", "hryvnia"]),
+    ("RUB", &["₽", "ruble", "rubles"]),
+    ("VND", &["₫", "dong"]),
+    ("NGN", &["₦", "naira"]),
+];
+
+/// A set of exchange rates, as the amount of each currency that one unit of the base is worth
+#[derive(Debug, Clone)]
+pub struct Rates {
+    pub base: String,
+    pub date: String,
+    pub rates: Vec<(String, f64)>,
+}
+
+/// The layout of JSON rate exports, like those from the ECB or frankfurter.app
+#[derive(Deserialize)]
+struct JsonRates {
+    #[serde(default = "default_base")]
+    base: String,
+    date: String,
+    rates: HashMap<String, f64>,
+}
+
+fn default_base() -> String {
+    "EUR".to_string()
+}
+
+/// The folder that the rates file is read from
+pub fn rates_dir() -> String {
+    std::env::var("HOME").unwrap_or("".to_owned()) + "/.config/rustcast"
+}
+
+/// Load the rates file from the rustcast config directory, if there is one
+pub fn load_rates() -> Option<Result<Rates, String>> {
+    let dir = rates_dir();
+    if let Ok(xml) = std::fs::read_to_string(dir.clone() + "/rates.xml") {
+        return Some(parse_ecb_xml(&xml));
+    }
+    if let Ok(json) = std::fs::read_to_string(dir + "/rates.json") {
+        return Some(parse_json(&json));
+    }
+    None
+}
+
+/// Parse a JSON export with a `base`, `date` and a map of `rates`
+pub fn parse_json(json: &str) -> Result<Rates, String> {
+    let parsed: JsonRates = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let mut rates: Vec<(String, f64)> = parsed.rates.into_iter().collect();
+    rates.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(Rates {
+        base: parsed.base.to_uppercase(),
+        date: parsed.date,
+        rates,
+    })
+}
+
+/// Parse the ECB's `eurofxref` XML, where rates are nested in
+/// `<Cube time="2024-05-03"><Cube currency="USD" rate="1.0762"/>...</Cube>`
+///
+/// Files with history in them only have their newest (first) day read
+pub fn parse_ecb_xml(xml: &str) -> Result<Rates, String> {
+    let mut date = None;
+    let mut rates = Vec::new();
+
+    for tag in xml.split('<').skip(1) {
+        let tag = tag.split('>').next().unwrap_or("");
+        if !tag.starts_with("Cube") {
+            continue;
+        }
+        if let Some(time) = xml_attribute(tag, "time") {
+            if date.is_some() {
+                break;
+            }
+            date = Some(time.to_string());
+        }
+        if let (Some(currency), Some(rate)) =
+            (xml_attribute(tag, "currency"), xml_attribute(tag, "rate"))
+        {
+            let rate = rate
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid rate for {currency}: {rate}"))?;
+            rates.push((currency.to_uppercase(), rate));
+        }
+    }
+
+    Ok(Rates {
+        base: "EUR".to_string(),
+        date: date.ok_or("No date in the rates file")?,
+        rates,
+    })
+}
+
+/// Get the value of an attribute in a tag, which can be quoted with either `'` or `"`
+fn xml_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {name}="))? + name.len() + 2;
+    let quote = tag[start..].chars().next()?;
+    let rest = &tag[start + 1..];
+    Some(&rest[..rest.find(quote)?])
+}
+
+/// How many days old rates from a `YYYY-MM-DD` date are
+pub fn age_in_days(date: &str) -> Option<i64> {
+    let mut parts = date.get(..10)?.split('-').map(|part| part.parse::<i64>());
+    let (year, month, day) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+
+    let today = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs() as i64 / 86_400;
+    Some(today - days_from_civil(year, month, day))
+}
+
+/// The number of days since 1970-01-01 for a date in the proleptic gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
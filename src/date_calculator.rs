@@ -0,0 +1,426 @@
+//! Date arithmetic and timestamp conversion
+//!
+//! The calculator and unit conversion providers leave out the queries that this answers, so that
+//! dates like `2026-03-01` aren't read as subtractions
+//!
+//! Examples:
+//!   "today + 45 days"           => the date in 45 days
+//!   "days until 2027-01-01"     => the number of days left
+//!   "2026-03-01 - 2025-12-24"   => 67 days
+//!   "1700000000"                => the date of a unix timestamp
+//!   "now as unix"               => the current unix timestamp
+//!   "2026-10-18T12:00:00Z as rfc2822" => the date in another format
+//!   "week of 2026-03-01"        => the ISO week number
+use jiff::{
+    Span, Timestamp, Unit, Zoned,
+    civil::{Date, DateTime, ISOWeekDate, Weekday},
+    fmt::rfc2822,
+    tz::TimeZone,
+};
+
+/// A result of a date query, as a row that can be copied
+#[derive(Debug, Clone)]
+pub struct DateRow {
+    pub value: String,
+    pub description: String,
+}
+
+impl DateRow {
+    fn new(value: impl Into<String>, description: impl Into<String>) -> DateRow {
+        DateRow {
+            value: value.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// A point in time, which is either a whole day or an exact instant
+#[derive(Debug, Clone)]
+enum Moment {
+    Day(Date),
+    Instant(Zoned),
+}
+
+impl Moment {
+    fn to_zoned(&self) -> Result<Zoned, String> {
+        match self {
+            Moment::Day(date) => date.to_zoned(TimeZone::system()).map_err(|e| e.to_string()),
+            Moment::Instant(zoned) => Ok(zoned.clone()),
+        }
+    }
+
+    fn date(&self) -> Date {
+        match self {
+            Moment::Day(date) => *date,
+            Moment::Instant(zoned) => zoned.date(),
+        }
+    }
+}
+
+/// The formats that a moment can be shown in with `as <format>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Unix,
+    Iso,
+    Rfc2822,
+    Week,
+}
+
+/// Answer a date query, returning [`None`] if the query isn't about dates at all
+pub fn date_query(query: &str) -> Option<Result<Vec<DateRow>, String>> {
+    let query = query.trim();
+    let lower = query.to_lowercase();
+
+    if let Some(rows) = week_query(&lower) {
+        return Some(rows);
+    }
+    if let Some(rows) = countdown_query(&lower) {
+        return Some(rows);
+    }
+
+    // `<moment> as <format>`
+    let (rest, format) = match split_format(query) {
+        Some((rest, format)) => (rest, Some(format)),
+        None => (query, None),
+    };
+
+    if let Some(moment) = parse_moment(rest, format.is_some()) {
+        return Some(format_moment(&moment, format));
+    }
+
+    let tokens: Vec<&str> = rest.split_whitespace().collect();
+    let (first, ops) = tokens.split_first()?;
+    let first = parse_moment(first, true)?;
+
+    // `<moment> - <moment>`
+    if let [sign, other] = ops
+        && *sign == "-"
+        && let Some(other) = parse_moment(other, true)
+    {
+        return Some(difference(&first, &other));
+    }
+
+    let moment = match add_spans(first, ops)? {
+        Ok(moment) => moment,
+        Err(e) => return Some(Err(e)),
+    };
+    Some(format_moment(&moment, format))
+}
+
+/// Parse a moment on its own, which is a date, a date and time, or a unix timestamp
+///
+/// `now` is only a moment when `allow_now` is set, so that a plain `now` is left to the world
+/// clock
+fn parse_moment(s: &str, allow_now: bool) -> Option<Moment> {
+    let today = Zoned::now().date();
+    match s.to_lowercase().as_str() {
+        "now" if allow_now => return Some(Moment::Instant(Zoned::now())),
+        "today" => return Some(Moment::Day(today)),
+        "tomorrow" => return today.tomorrow().ok().map(Moment::Day),
+        "yesterday" => return today.yesterday().ok().map(Moment::Day),
+        _ => {}
+    }
+
+    // Unix timestamps, in seconds or milliseconds
+    let digits = s.strip_prefix('@').unwrap_or(s);
+    if digits.chars().all(|c| c.is_ascii_digit()) {
+        let timestamp = match digits.len() {
+            9 | 10 => Timestamp::from_second(digits.parse().ok()?).ok()?,
+            12 | 13 => Timestamp::from_millisecond(digits.parse().ok()?).ok()?,
+            _ => return None,
+        };
+        return Some(Moment::Instant(timestamp.to_zoned(TimeZone::system())));
+    }
+
+    if let Ok(zoned) = s.parse::<Zoned>() {
+        return Some(Moment::Instant(zoned));
+    }
+    if let Ok(timestamp) = s.parse::<Timestamp>() {
+        return Some(Moment::Instant(timestamp.to_zoned(TimeZone::system())));
+    }
+    // Dates would otherwise be read from the start of a date and time
+    if !s.contains(['T', 't', ' '])
+        && let Ok(date) = s.parse::<Date>()
+    {
+        return Some(Moment::Day(date));
+    }
+    if let Ok(datetime) = s.parse::<DateTime>() {
+        return datetime
+            .to_zoned(TimeZone::system())
+            .ok()
+            .map(Moment::Instant);
+    }
+    if let Ok(zoned) = rfc2822::parse(s) {
+        return Some(Moment::Instant(zoned));
+    }
+    None
+}
+
+/// Split `as <format>` (or `to`/`in <format>`) from the end of a query
+fn split_format(query: &str) -> Option<(&str, Format)> {
+    let lower = query.to_lowercase();
+    for word in [" as ", " to ", " in "] {
+        let Some(i) = lower.rfind(word) else {
+            continue;
+        };
+        let format = match lower[i + word.len()..].trim() {
+            "unix" | "timestamp" | "unix timestamp" | "epoch" => Format::Unix,
+            "iso" | "iso8601" | "iso 8601" => Format::Iso,
+            "rfc2822" | "rfc 2822" | "rfc" => Format::Rfc2822,
+            "week" | "week number" => Format::Week,
+            _ => continue,
+        };
+        return Some((query[..i].trim(), format));
+    }
+    None
+}
+
+/// Apply `+ <n> <unit>` and `- <n> <unit>` to a moment, returning [`None`] if the rest of the
+/// query isn't made of them
+fn add_spans(mut moment: Moment, mut ops: &[&str]) -> Option<Result<Moment, String>> {
+    if ops.is_empty() {
+        return None;
+    }
+
+    while !ops.is_empty() {
+        // The sign can be its own word or stuck to the number, like `+ 3 days` or `+3 days`
+        let (sign, rest) = match ops[0] {
+            "+" => (1, &ops[1..]),
+            "-" => (-1, &ops[1..]),
+            op if op.starts_with('+') => (1, ops),
+            op if op.starts_with('-') => (-1, ops),
+            _ => return None,
+        };
+        let (amount, unit, rest) = match rest {
+            // `45 days` or `45days`
+            [amount, unit, rest @ ..] if parse_amount(amount).is_some() => {
+                (parse_amount(amount)?, *unit, rest)
+            }
+            [amount_unit, rest @ ..] => {
+                let amount_unit = amount_unit.trim_start_matches(['+', '-']);
+                let split = amount_unit
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(amount_unit.len());
+                (
+                    amount_unit[..split].parse().ok()?,
+                    &amount_unit[split..],
+                    rest,
+                )
+            }
+            [] => return None,
+        };
+        let span = match to_span(sign * amount, unit)? {
+            Ok(span) => span,
+            Err(e) => return Some(Err(e)),
+        };
+
+        moment = match moment {
+            // Whole days stay whole days, unless hours or minutes get involved
+            Moment::Day(date) if span.get_hours() == 0 && span.get_minutes() == 0 => {
+                match date.checked_add(span) {
+                    Ok(date) => Moment::Day(date),
+                    Err(e) => return Some(Err(e.to_string())),
+                }
+            }
+            moment => match moment
+                .to_zoned()
+                .and_then(|zoned| zoned.checked_add(span).map_err(|e| e.to_string()))
+            {
+                Ok(zoned) => Moment::Instant(zoned),
+                Err(e) => return Some(Err(e)),
+            },
+        };
+        ops = rest;
+    }
+    Some(Ok(moment))
+}
+
+/// Parse a whole number, ignoring its sign as that has already been read
+fn parse_amount(amount: &str) -> Option<i64> {
+    amount.trim_start_matches(['+', '-']).parse().ok()
+}
+
+/// Make a span of a number of units, returning [`None`] if the unit isn't a unit of time
+fn to_span(amount: i64, unit: &str) -> Option<Result<Span, String>> {
+    let span = Span::new();
+    let span = match unit.to_lowercase().as_str() {
+        "y" | "yr" | "yrs" | "year" | "years" => span.try_years(amount),
+        "mo" | "month" | "months" => span.try_months(amount),
+        "w" | "wk" | "wks" | "week" | "weeks" => span.try_weeks(amount),
+        "d" | "day" | "days" => span.try_days(amount),
+        "h" | "hr" | "hrs" | "hour" | "hours" => span.try_hours(amount),
+        "min" | "mins" | "minute" | "minutes" => span.try_minutes(amount),
+        _ => return None,
+    };
+    Some(span.map_err(|e| e.to_string()))
+}
+
+/// `days until <moment>`, `weeks since <moment>` and the like
+fn countdown_query(query: &str) -> Option<Result<Vec<DateRow>, String>> {
+    let (unit, rest) = query.split_once(' ')?;
+    let (since, moment) = match rest.split_once(' ')? {
+        ("until" | "till" | "to", moment) => (false, moment),
+        ("since" | "from", moment) => (true, moment),
+        _ => return None,
+    };
+    let unit = match unit {
+        "days" => Unit::Day,
+        "weeks" => Unit::Week,
+        "months" => Unit::Month,
+        "years" => Unit::Year,
+        "hours" => Unit::Hour,
+        "minutes" => Unit::Minute,
+        _ => return None,
+    };
+    let moment = parse_moment(moment.trim(), true)?;
+
+    let now = Zoned::now();
+    let span = match &moment {
+        Moment::Day(date) if unit >= Unit::Day => {
+            now.date().until((unit, *date)).map_err(|e| e.to_string())
+        }
+        moment => moment
+            .to_zoned()
+            .and_then(|zoned| now.until((unit, &zoned)).map_err(|e| e.to_string())),
+    };
+    let span = match span {
+        Ok(span) => span,
+        Err(e) => return Some(Err(e)),
+    };
+
+    let count = span_in_unit(&span, unit);
+    let count = if since { -count } else { count };
+    let unit = format!("{unit:?}").to_lowercase();
+    Some(Ok(vec![DateRow::new(
+        plural(count, &unit),
+        describe_date(&moment),
+    )]))
+}
+
+/// `week`, `week number`, `week of <moment>` or `week <moment>`
+fn week_query(query: &str) -> Option<Result<Vec<DateRow>, String>> {
+    let rest = query.strip_prefix("week")?.trim();
+    let moment = match rest {
+        "" | "number" => Moment::Day(Zoned::now().date()),
+        rest => parse_moment(rest.strip_prefix("of ").unwrap_or(rest).trim(), true)?,
+    };
+    Some(Ok(vec![week_row(moment.date())]))
+}
+
+/// The difference between two moments, in days (and in weeks and days if it's long enough)
+fn difference(first: &Moment, other: &Moment) -> Result<Vec<DateRow>, String> {
+    let (days, description) = match (first, other) {
+        (Moment::Day(first), Moment::Day(other)) => {
+            let span = other
+                .until((Unit::Day, *first))
+                .map_err(|e| e.to_string())?;
+            (span.get_days() as i64, None)
+        }
+        (first, other) => {
+            let span = other
+                .to_zoned()?
+                .until((Unit::Day, &first.to_zoned()?))
+                .map_err(|e| e.to_string())?;
+            let (hours, minutes) = (span.get_hours() as i64, span.get_minutes());
+            let time = match (hours, minutes) {
+                (0, 0) => None,
+                (hours, 0) => Some(plural(hours, "hour")),
+                (hours, minutes) => Some(format!(
+                    "{} {}",
+                    plural(hours, "hour"),
+                    plural(minutes, "minute")
+                )),
+            };
+            (span.get_days() as i64, time)
+        }
+    };
+
+    let mut description = description.unwrap_or_default();
+    if days.abs() >= 7 {
+        let weeks = format!("{} {}", plural(days / 7, "week"), plural(days % 7, "day"));
+        description = if description.is_empty() {
+            weeks
+        } else {
+            format!("{weeks} {description}")
+        };
+    }
+    Ok(vec![DateRow::new(plural(days, "day"), description)])
+}
+
+/// Show a moment as rows, either in every useful format or only in the one that was asked for
+fn format_moment(moment: &Moment, format: Option<Format>) -> Result<Vec<DateRow>, String> {
+    let zoned = moment.to_zoned()?;
+    let unix = DateRow::new(zoned.timestamp().as_second().to_string(), "Unix timestamp");
+    let iso = DateRow::new(
+        match moment {
+            Moment::Day(date) => date.to_string(),
+            Moment::Instant(zoned) => zoned.strftime("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+        },
+        "ISO 8601",
+    );
+    let rfc = DateRow::new(
+        rfc2822::to_string(&zoned).map_err(|e| e.to_string())?,
+        "RFC 2822",
+    );
+
+    Ok(match format {
+        Some(Format::Unix) => vec![unix],
+        Some(Format::Iso) => vec![iso],
+        Some(Format::Rfc2822) => vec![rfc],
+        Some(Format::Week) => vec![week_row(moment.date())],
+        None => match moment {
+            Moment::Day(_) => vec![DateRow::new(describe_date(moment), iso.value)],
+            Moment::Instant(_) => vec![
+                DateRow::new(describe_date(moment), "Local time"),
+                iso,
+                rfc,
+                unix,
+            ],
+        },
+    })
+}
+
+/// A moment written out for people, like `Wed 2 Dec 2026` or `Tue 14 Nov 2023, 10:13 PM`
+fn describe_date(moment: &Moment) -> String {
+    match moment {
+        Moment::Day(date) => date.strftime("%a %-d %b %Y").to_string(),
+        Moment::Instant(zoned) => zoned
+            .with_time_zone(TimeZone::system())
+            .strftime("%a %-d %b %Y, %-I:%M %p")
+            .to_string(),
+    }
+}
+
+/// The ISO week that a date is in, and the days that the week covers
+fn week_row(date: Date) -> DateRow {
+    let week = date.iso_week_date();
+    let monday = ISOWeekDate::new(week.year(), week.week(), Weekday::Monday)
+        .map(|monday| monday.date())
+        .unwrap_or(date);
+    let sunday = monday.checked_add(Span::new().days(6)).unwrap_or(monday);
+    DateRow::new(
+        format!("Week {} of {}", week.week(), week.year()),
+        format!("{monday} to {sunday}"),
+    )
+}
+
+/// How many of a unit a span is, as returned by `until` with that unit as the largest
+fn span_in_unit(span: &Span, unit: Unit) -> i64 {
+    match unit {
+        Unit::Year => span.get_years() as i64,
+        Unit::Month => span.get_months() as i64,
+        Unit::Week => span.get_weeks() as i64,
+        Unit::Day => span.get_days() as i64,
+        Unit::Hour => span.get_hours() as i64,
+        _ => span.get_minutes(),
+    }
+}
+
+/// A count with its unit, like `1 day` or `45 days`
+fn plural(count: i64, unit: &str) -> String {
+    if count.abs() == 1 {
+        format!("{count} {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
@@ -0,0 +1,488 @@
+//! The extension host, which runs the extensions from the config as processes and talks to them
+//! with JSON-RPC over their stdin and stdout
+//!
+//! Every message is a JSON-RPC 2.0 object on a line of its own. See `EXTENSIONS.md` for the
+//! protocol.
+//!
+//! Each request waits for at most the extension's timeout, and an extension that exits or doesn't
+//! answer in time is started again on the next request, unless that happened too often, so a
+//! broken extension can't hang the tile.
+pub mod wasm;
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use iced::futures::channel::mpsc::Sender;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    app::{
+        Message,
+        apps::{App, AppCommand},
+        pages::generic::View,
+    },
+    clipboard::ClipBoardContentType,
+    commands::Function,
+    config::ExtensionConfig,
+    shell::expand_home,
+    utils::{handle_from_path, log_error, show_notification},
+};
+
+/// The version of the protocol, which is sent to extensions when they are started
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// How long extensions have to answer a request, if their config doesn't say
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Where the rows of extensions are ranked, if their config doesn't say
+const DEFAULT_PRIORITY: u8 = 60;
+
+/// How many times an extension can be started again within [`RESTART_WINDOW`], after which it
+/// is left stopped until the window has passed
+const MAX_RESTARTS: usize = 3;
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+
+/// The extensions from the config, so that commands can find the extension of their item
+static EXTENSIONS: Mutex<Vec<Arc<Extension>>> = Mutex::new(Vec::new());
+
+/// Where updates that extensions push are sent, once the tile has given its sender
+static SENDER: Mutex<Option<Sender<Message>>> = Mutex::new(None);
+
+/// An item that an extension returns for a search
+#[derive(Debug, Clone, Deserialize)]
+pub struct Item {
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub action: ItemAction,
+}
+
+/// What happens when an item is opened
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ItemAction {
+    /// Send the item back to the extension with a `run` request
+    #[default]
+    Run,
+    Copy {
+        text: String,
+    },
+    Open {
+        url: String,
+    },
+    /// Show a view, whose rows are items of the same extension
+    Push {
+        view: Box<View<Item>>,
+    },
+}
+
+/// The requests that are waiting for a response, by their id
+type Pending = Arc<Mutex<HashMap<u64, mpsc::Sender<Result<Value, String>>>>>;
+
+impl Item {
+    /// Turn an item of an extension into an app
+    pub fn into_app(self, extension: &str) -> App {
+        let open_command = match self.action {
+            ItemAction::Run => {
+                AppCommand::Function(Function::RunExtensionItem(extension.to_string(), self.id))
+            }
+            ItemAction::Copy { text } => {
+                AppCommand::Function(Function::CopyToClipboard(ClipBoardContentType::Text(text)))
+            }
+            ItemAction::Open { url } => AppCommand::Function(Function::OpenWebsite(url)),
+            ItemAction::Push { view } => {
+                AppCommand::Message(Message::PushView(into_view(*view, extension)))
+            }
+        };
+        App {
+            open_command,
+            desc: self.subtitle.unwrap_or(extension.to_string()),
+            icons: self.icon.as_deref().and_then(handle_from_path),
+            name: self.title,
+            name_lc: String::new(),
+        }
+    }
+}
+
+/// Turn a view that an extension sent into one that can be shown, with its forms sent back to
+/// the extension
+pub fn into_view(view: View<Item>, extension: &str) -> View {
+    let view = view.map_rows(|item| item.into_app(extension));
+    match view {
+        View::Form {
+            title, id, fields, ..
+        } => View::Form {
+            title,
+            id,
+            fields,
+            extension: Some(extension.to_string()),
+        },
+        view => view,
+    }
+}
+
+/// The items of a search or an update
+#[derive(Debug, Deserialize)]
+struct Items {
+    query: Option<String>,
+    items: Vec<Item>,
+}
+
+/// A running extension process
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    pending: Pending,
+    next_id: u64,
+}
+
+impl Process {
+    /// Write a request, and return where its response will arrive
+    fn send(
+        &mut self,
+        method: &str,
+        params: Value,
+    ) -> Result<mpsc::Receiver<Result<Value, String>>, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(id, sender);
+        }
+
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        writeln!(self.stdin, "{request}")
+            .and_then(|_| self.stdin.flush())
+            .map_err(|error| error.to_string())?;
+        Ok(receiver)
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// An extension from the config, which is started when it is first needed
+pub struct Extension {
+    pub config: ExtensionConfig,
+    process: Mutex<Option<Process>>,
+    starts: Mutex<Vec<Instant>>,
+}
+
+impl Extension {
+    fn new(config: ExtensionConfig) -> Extension {
+        Extension {
+            config,
+            process: Mutex::new(None),
+            starts: Mutex::new(vec![]),
+        }
+    }
+
+    fn timeout(&self) -> Duration {
+        self.config
+            .timeout
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Where the rows of the extension are ranked
+    pub fn priority(&self) -> u8 {
+        self.config.priority.unwrap_or(DEFAULT_PRIORITY)
+    }
+
+    /// Search the extension, and turn its items into apps
+    pub fn search(&self, query: &str) -> Result<Vec<App>, String> {
+        let result = self.request("search", json!({ "query": query }))?;
+        let items: Items = serde_json::from_value(result)
+            .map_err(|error| format!("{} sent bad items: {error}", self.config.name))?;
+        Ok(items
+            .items
+            .into_iter()
+            .map(|item| item.into_app(&self.config.name))
+            .collect())
+    }
+
+    /// Send a request to the extension, and wait for its response
+    fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let name = &self.config.name;
+        let (receiver, pending) = {
+            let mut process = self
+                .process
+                .lock()
+                .map_err(|_| format!("{name} is unavailable"))?;
+            let running = process
+                .as_mut()
+                .is_some_and(|process| matches!(process.child.try_wait(), Ok(None)));
+            if !running {
+                *process = Some(self.start()?);
+            }
+
+            let Some(running) = process.as_mut() else {
+                return Err(format!("{name} isn't running"));
+            };
+            match running.send(method, params) {
+                Ok(receiver) => (receiver, running.pending.clone()),
+                Err(error) => {
+                    *process = None;
+                    return Err(format!("Couldn't write to {name}: {error}"));
+                }
+            }
+        };
+
+        match receiver.recv_timeout(self.timeout()) {
+            Ok(response) => response,
+            Err(RecvTimeoutError::Timeout) => {
+                // An extension that is stuck would hold up every request after this one, so it is
+                // stopped and started again on the next request
+                if let Ok(mut process) = self.process.lock()
+                    && process
+                        .as_ref()
+                        .is_some_and(|process| Arc::ptr_eq(&process.pending, &pending))
+                {
+                    *process = None;
+                }
+                Err(format!("{name} didn't answer {method} in time"))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(format!("{name} exited")),
+        }
+    }
+
+    /// Start the extension's process, and send it the `initialize` request
+    fn start(&self) -> Result<Process, String> {
+        let name = &self.config.name;
+        if let Ok(mut starts) = self.starts.lock() {
+            starts.retain(|start| start.elapsed() < RESTART_WINDOW);
+            if starts.len() > MAX_RESTARTS {
+                return Err(format!("{name} failed too often, and is stopped for now"));
+            }
+            starts.push(Instant::now());
+        }
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&self.config.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(cwd) = &self.config.cwd {
+            command.current_dir(expand_home(cwd));
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|error| format!("Couldn't start {name}: {error}"))?;
+
+        let (Some(stdin), Some(stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            return Err(format!("Couldn't talk to {name}"));
+        };
+
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        spawn_reader(name.clone(), stdout, pending.clone());
+        let log_name = name.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                log_error(&format!("[{log_name}] {line}\n"));
+            }
+        });
+
+        let mut process = Process {
+            child,
+            stdin,
+            pending,
+            next_id: 0,
+        };
+        let receiver = process.send(
+            "initialize",
+            json!({
+                "protocol_version": PROTOCOL_VERSION,
+                "rustcast_version": env!("CARGO_PKG_VERSION"),
+            }),
+        )?;
+        match receiver.recv_timeout(self.timeout()) {
+            Ok(Ok(_)) => Ok(process),
+            Ok(Err(error)) => Err(format!("{name} failed to initialize: {error}")),
+            Err(_) => Err(format!("{name} didn't initialize in time")),
+        }
+    }
+}
+
+/// Read the messages from an extension: responses go to the requests waiting for them, and
+/// notifications are handled as they come
+fn spawn_reader(name: String, stdout: impl std::io::Read + Send + 'static, pending: Pending) {
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                log_error(&format!(
+                    "[{name}] sent something that isn't JSON: {line}\n"
+                ));
+                continue;
+            };
+
+            if let Some(id) = message.get("id").and_then(Value::as_u64)
+                && message.get("method").is_none()
+            {
+                let response = match message.get("error") {
+                    Some(error) => Err(error
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unknown error")
+                        .to_string()),
+                    None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                if let Some(sender) = pending.lock().ok().and_then(|mut p| p.remove(&id)) {
+                    sender.send(response).ok();
+                }
+            } else if let Some(method) = message.get("method").and_then(Value::as_str) {
+                handle_notification(&name, method, message.get("params").unwrap_or(&Value::Null));
+            }
+        }
+
+        // The extension exited, so nothing that is waiting will get an answer
+        if let Ok(mut pending) = pending.lock() {
+            pending.clear();
+        }
+    });
+}
+
+/// Handle a notification that an extension pushed
+fn handle_notification(name: &str, method: &str, params: &Value) {
+    match method {
+        "update" => {
+            let Ok(items) = serde_json::from_value::<Items>(params.clone()) else {
+                log_error(&format!("[{name}] sent a bad update\n"));
+                return;
+            };
+            let Some(extension) = find(name) else {
+                return;
+            };
+            let apps = items
+                .items
+                .into_iter()
+                .map(|item| item.into_app(name))
+                .collect();
+            send_message(Message::ExtensionUpdate(
+                items.query.unwrap_or_default(),
+                name.to_string(),
+                extension.priority(),
+                apps,
+            ));
+        }
+        "notify" => show_notification(
+            params.get("title").and_then(Value::as_str).unwrap_or(name),
+            params.get("body").and_then(Value::as_str).unwrap_or(""),
+        ),
+        "log" => log_error(&format!(
+            "[{name}] {}\n",
+            params.get("message").and_then(Value::as_str).unwrap_or("")
+        )),
+        method => log_error(&format!(
+            "[{name}] sent an unknown notification: {method}\n"
+        )),
+    }
+}
+
+/// Replace the extensions with the ones in the config, which stops the old ones
+///
+/// An extension whose config didn't change is kept as it is, so reloading the config doesn't
+/// restart its process
+pub fn load_extensions(configs: &[ExtensionConfig]) -> Vec<Arc<Extension>> {
+    let loaded = EXTENSIONS
+        .lock()
+        .map(|loaded| loaded.clone())
+        .unwrap_or_default();
+    let extensions: Vec<Arc<Extension>> = configs
+        .iter()
+        .map(|config| {
+            loaded
+                .iter()
+                .find(|extension| extension.config == *config)
+                .cloned()
+                .unwrap_or_else(|| Arc::new(Extension::new(config.clone())))
+        })
+        .collect();
+    if let Ok(mut loaded) = EXTENSIONS.lock() {
+        *loaded = extensions.clone();
+    }
+    extensions
+}
+
+/// The extension with a name
+fn find(name: &str) -> Option<Arc<Extension>> {
+    EXTENSIONS
+        .lock()
+        .ok()?
+        .iter()
+        .find(|extension| extension.config.name == name)
+        .cloned()
+}
+
+/// Send an item back to its extension to be run, without waiting for the extension
+pub fn run_item(name: &str, id: &str) {
+    let Some(extension) = find(name) else {
+        wasm::run_item(name, id);
+        return;
+    };
+    let id = id.to_string();
+    thread::spawn(
+        move || match extension.request("run", json!({ "id": id })) {
+            Ok(result) => push_view(&extension.config.name, result),
+            Err(error) => log_error(&format!("{error}\n")),
+        },
+    );
+}
+
+/// Send the values of a form back to its extension with a `submit` request
+pub fn submit_form(name: &str, id: &str, values: serde_json::Map<String, Value>) {
+    let Some(extension) = find(name) else {
+        return;
+    };
+    let id = id.to_string();
+    thread::spawn(move || {
+        match extension.request("submit", json!({ "id": id, "values": values })) {
+            Ok(result) => push_view(&extension.config.name, result),
+            Err(error) => log_error(&format!("{error}\n")),
+        }
+    });
+}
+
+/// Show the view in the result of a request, if it has one
+fn push_view(name: &str, result: Value) {
+    let Some(view) = result.get("view") else {
+        return;
+    };
+    match serde_json::from_value::<View<Item>>(view.clone()) {
+        Ok(view) => send_message(Message::PushView(into_view(view, name))),
+        Err(error) => log_error(&format!("{name} sent a view that isn't valid: {error}\n")),
+    }
+}
+
+/// Set where the updates that extensions push are sent
+pub fn set_sender(sender: Sender<Message>) {
+    if let Ok(mut current) = SENDER.lock() {
+        *current = Some(sender);
+    }
+}
+
+fn send_message(message: Message) {
+    if let Some(sender) = SENDER.lock().ok().and_then(|sender| sender.clone()) {
+        sender.clone().try_send(message).ok();
+    }
+}
@@ -0,0 +1,493 @@
+//! WASM extensions, which are components that run inside rustcast instead of as processes
+//!
+//! Each extension is a directory in `~/.config/rustcast/extensions`, with an `extension.toml`
+//! manifest and the component it names. The interface between rustcast and the component is in
+//! `wit/extension.wit`, and everything the component asks rustcast to do (reading files, the
+//! clipboard, running commands) is checked against the capabilities in its manifest:
+//!
+//! ```toml
+//! name = "Notes"
+//! component = "notes.wasm"
+//! keyword = "note"
+//!
+//! [capabilities]
+//! read = ["~/Documents/Notes"]
+//! write = []
+//! clipboard = true
+//! commands = ["git"]
+//!
+//! [config]
+//! sort = "modified"
+//! ```
+//!
+//! Calls into a component are stopped once they run for longer than its timeout, and a component
+//! that fails is instantiated again on the next call, unless that happened too often.
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, LazyLock, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use arboard::Clipboard;
+use serde::Deserialize;
+use wasmtime::{
+    Engine, Store, StoreLimits, StoreLimitsBuilder,
+    component::{Component, HasSelf, Linker},
+};
+
+use super::{DEFAULT_PRIORITY, DEFAULT_TIMEOUT, ItemAction, MAX_RESTARTS, RESTART_WINDOW};
+use crate::{app::apps::App, shell::expand_home, utils::log_error};
+
+wasmtime::component::bindgen!({ world: "extension", path: "wit" });
+
+use rustcast::extension::{host, types};
+
+/// How often the engine's epoch goes up, which is how the timeouts of calls are measured
+const TICK: Duration = Duration::from_millis(10);
+
+/// How much memory a component can use
+const MEMORY_LIMIT: usize = 64 << 20;
+
+/// The engine that every component runs on, with a thread that moves its epoch on
+static ENGINE: LazyLock<Result<Engine, String>> = LazyLock::new(|| {
+    let mut config = wasmtime::Config::new();
+    config.epoch_interruption(true);
+    let engine = Engine::new(&config).map_err(|error| error.to_string())?;
+
+    let ticker = engine.clone();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(TICK);
+            ticker.increment_epoch();
+        }
+    });
+    Ok(engine)
+});
+
+/// The extensions from the extensions directory, so that commands can find the extension of their
+/// item
+static EXTENSIONS: Mutex<Vec<Arc<WasmExtension>>> = Mutex::new(Vec::new());
+
+/// The `extension.toml` of an extension
+///
+/// - Name is what the extension's rows are described as, and must be unique
+/// - Component is the file name of the component, `extension.wasm` if not set
+/// - Keyword, priority and timeout work like they do for the extensions in the config
+/// - Capabilities are what the component is allowed to do
+/// - Config is given to the component when it asks for it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub component: Option<String>,
+    pub keyword: Option<String>,
+    pub priority: Option<u8>,
+    pub timeout: Option<u64>,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+    #[serde(default)]
+    pub config: toml::Table,
+}
+
+/// What an extension is allowed to do, which is nothing unless its manifest says so
+///
+/// - Read is the paths that it can read and list (and everything under them)
+/// - Write is the paths that it can write to
+/// - Clipboard is whether it can read and copy text
+/// - Commands is the programs that it can run
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    pub read: Vec<String>,
+    pub write: Vec<String>,
+    pub clipboard: bool,
+    pub commands: Vec<String>,
+}
+
+/// What the host functions of a component have access to
+struct HostState {
+    name: String,
+    capabilities: Capabilities,
+    config: toml::Table,
+    timeout: Duration,
+    limits: StoreLimits,
+}
+
+impl HostState {
+    /// The path, if it is under one of the paths that were granted
+    ///
+    /// Paths are compared once symlinks and `..` are resolved, so a path can't get out of the
+    /// granted ones. A path that doesn't exist yet (like a file that is about to be written) is
+    /// checked by its directory.
+    fn allowed(&self, path: &str, granted: &[String], verb: &str) -> Result<PathBuf, String> {
+        let path = PathBuf::from(expand_home(path));
+        let resolved =
+            path.canonicalize()
+                .or_else(|error| match (path.parent(), path.file_name()) {
+                    (Some(parent), Some(file)) => parent.canonicalize().map(|dir| dir.join(file)),
+                    _ => Err(error),
+                });
+
+        if let Ok(resolved) = resolved
+            && granted
+                .iter()
+                .filter_map(|granted| PathBuf::from(expand_home(granted)).canonicalize().ok())
+                .any(|granted| resolved.starts_with(granted))
+        {
+            return Ok(resolved);
+        }
+        Err(format!(
+            "{} isn't allowed to {verb} {}",
+            self.name,
+            path.display()
+        ))
+    }
+
+    fn clipboard(&self) -> Result<Clipboard, String> {
+        if !self.capabilities.clipboard {
+            return Err(format!("{} isn't allowed to use the clipboard", self.name));
+        }
+        Clipboard::new().map_err(|error| error.to_string())
+    }
+}
+
+impl types::Host for HostState {}
+
+impl host::Host for HostState {
+    fn get_config(&mut self, key: String) -> Option<String> {
+        self.config.get(&key).map(|value| match value {
+            toml::Value::String(text) => text.clone(),
+            value => value.to_string(),
+        })
+    }
+
+    fn read_file(&mut self, path: String) -> Result<Vec<u8>, String> {
+        let path = self.allowed(&path, &self.capabilities.read, "read")?;
+        fs::read(path).map_err(|error| error.to_string())
+    }
+
+    fn list_dir(&mut self, path: String) -> Result<Vec<String>, String> {
+        let path = self.allowed(&path, &self.capabilities.read, "read")?;
+        let mut names = fs::read_dir(path)
+            .map_err(|error| error.to_string())?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    }
+
+    fn write_file(&mut self, path: String, contents: Vec<u8>) -> Result<(), String> {
+        let path = self.allowed(&path, &self.capabilities.write, "write")?;
+        fs::write(path, contents).map_err(|error| error.to_string())
+    }
+
+    fn read_clipboard(&mut self) -> Result<String, String> {
+        self.clipboard()?
+            .get_text()
+            .map_err(|error| error.to_string())
+    }
+
+    fn write_clipboard(&mut self, text: String) -> Result<(), String> {
+        self.clipboard()?
+            .set_text(text)
+            .map_err(|error| error.to_string())
+    }
+
+    fn run_command(
+        &mut self,
+        program: String,
+        args: Vec<String>,
+    ) -> Result<host::CommandOutput, String> {
+        if !self.capabilities.commands.contains(&program) {
+            return Err(format!("{} isn't allowed to run {program}", self.name));
+        }
+
+        let mut child = Command::new(&program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| format!("Couldn't run {program}: {error}"))?;
+        let read = |pipe: Option<Box<dyn Read + Send>>| {
+            thread::spawn(move || {
+                let mut text = String::new();
+                if let Some(mut pipe) = pipe {
+                    pipe.read_to_string(&mut text).ok();
+                }
+                text
+            })
+        };
+        let stdout = read(child.stdout.take().map(|out| Box::new(out) as _));
+        let stderr = read(child.stderr.take().map(|err| Box::new(err) as _));
+
+        // The command can't take longer than the call that runs it
+        let start = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status.code(),
+                Ok(None) if start.elapsed() > self.timeout => {
+                    child.kill().ok();
+                    child.wait().ok();
+                    break None;
+                }
+                Ok(None) => thread::sleep(TICK),
+                Err(_) => break None,
+            }
+        };
+
+        Ok(host::CommandOutput {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+
+    fn log(&mut self, message: String) {
+        log_error(&format!("[{}] {message}\n", self.name));
+    }
+}
+
+impl From<types::Item> for super::Item {
+    fn from(item: types::Item) -> super::Item {
+        super::Item {
+            id: item.id,
+            title: item.title,
+            subtitle: item.subtitle,
+            icon: item.icon,
+            action: match item.action {
+                types::Action::Run => ItemAction::Run,
+                types::Action::Copy(text) => ItemAction::Copy { text },
+                types::Action::Open(url) => ItemAction::Open { url },
+            },
+        }
+    }
+}
+
+/// An instantiated component, with its store
+struct Instance {
+    store: Store<HostState>,
+    bindings: Extension,
+}
+
+/// An extension from the extensions directory, which is compiled and instantiated when it is first
+/// needed
+pub struct WasmExtension {
+    pub manifest: Manifest,
+    dir: PathBuf,
+    /// When the component was last changed, as of when the manifest was read
+    modified: Option<SystemTime>,
+    instance: Mutex<Option<Instance>>,
+    starts: Mutex<Vec<Instant>>,
+}
+
+impl WasmExtension {
+    fn timeout(&self) -> Duration {
+        self.manifest
+            .timeout
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Where the rows of the extension are ranked
+    pub fn priority(&self) -> u8 {
+        self.manifest.priority.unwrap_or(DEFAULT_PRIORITY)
+    }
+
+    /// Search the extension, and turn its items into apps
+    pub fn search(&self, query: &str) -> Result<Vec<App>, String> {
+        let items = self.call(|store, bindings| bindings.call_search(store, query))?;
+        Ok(items
+            .into_iter()
+            .map(|item| super::Item::from(item).into_app(&self.manifest.name))
+            .collect())
+    }
+
+    /// Run an item of the extension
+    fn run(&self, id: &str) -> Result<(), String> {
+        self.call(|store, bindings| bindings.call_run(store, id))?
+    }
+
+    /// Call a function of the component, instantiating it first if it isn't yet
+    ///
+    /// The call traps once it runs for longer than the timeout, and a component that trapped is
+    /// thrown away, since its state can't be trusted anymore
+    fn call<T>(
+        &self,
+        call: impl FnOnce(&mut Store<HostState>, &Extension) -> wasmtime::Result<T>,
+    ) -> Result<T, String> {
+        let name = &self.manifest.name;
+        let mut instance = self
+            .instance
+            .lock()
+            .map_err(|_| format!("{name} is unavailable"))?;
+        if instance.is_none() {
+            *instance = Some(self.instantiate()?);
+        }
+        let Some(Instance { store, bindings }) = instance.as_mut() else {
+            return Err(format!("{name} isn't loaded"));
+        };
+
+        store.set_epoch_deadline(self.ticks());
+        call(store, bindings).map_err(|error| {
+            *instance = None;
+            format!("{name} failed: {error:#}")
+        })
+    }
+
+    /// How many ticks of the engine a call can take
+    fn ticks(&self) -> u64 {
+        (self.timeout().as_millis() / TICK.as_millis()).max(1) as u64
+    }
+
+    /// Compile and instantiate the component
+    fn instantiate(&self) -> Result<Instance, String> {
+        let name = &self.manifest.name;
+        if let Ok(mut starts) = self.starts.lock() {
+            starts.retain(|start| start.elapsed() < RESTART_WINDOW);
+            if starts.len() > MAX_RESTARTS {
+                return Err(format!("{name} failed too often, and is stopped for now"));
+            }
+            starts.push(Instant::now());
+        }
+
+        let engine = ENGINE.as_ref().map_err(|error| error.to_string())?;
+        let path = component_path(&self.manifest, &self.dir);
+        let component = Component::from_file(engine, &path)
+            .map_err(|error| format!("Couldn't load {}: {error:#}", path.display()))?;
+
+        let mut linker = Linker::new(engine);
+        Extension::add_to_linker::<HostState, HasSelf<HostState>>(&mut linker, |state| state)
+            .map_err(|error| error.to_string())?;
+
+        let mut store = Store::new(
+            engine,
+            HostState {
+                name: name.clone(),
+                capabilities: self.manifest.capabilities.clone(),
+                config: self.manifest.config.clone(),
+                timeout: self.timeout(),
+                limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_epoch_deadline(self.ticks());
+
+        let bindings = Extension::instantiate(&mut store, &component, &linker)
+            .map_err(|error| format!("Couldn't start {name}: {error:#}"))?;
+        Ok(Instance { store, bindings })
+    }
+}
+
+/// The directory that WASM extensions are read from
+pub fn extensions_dir() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap_or_default() + "/.config/rustcast/extensions")
+}
+
+/// The files of every extension, with when they were last changed
+///
+/// This is compared to the last one to tell whether the extensions need to be loaded again
+pub fn scan() -> Vec<(PathBuf, SystemTime)> {
+    let mut files = fs::read_dir(extensions_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .flat_map(|dir| fs::read_dir(dir.path()).into_iter().flatten().flatten())
+        .filter(|file| {
+            let path = file.path();
+            path.file_name()
+                .is_some_and(|name| name == "extension.toml")
+                || path
+                    .extension()
+                    .is_some_and(|extension| extension == "wasm")
+        })
+        .filter_map(|file| Some((file.path(), file.metadata().ok()?.modified().ok()?)))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Read the manifest of every extension, which replaces the extensions that were loaded before
+///
+/// An extension whose manifest and component didn't change is kept as it is, so that its instance
+/// doesn't have to be started again
+pub fn load_extensions() -> Vec<Arc<WasmExtension>> {
+    let loaded = EXTENSIONS
+        .lock()
+        .map(|loaded| loaded.clone())
+        .unwrap_or_default();
+    let extensions: Vec<Arc<WasmExtension>> = fs::read_dir(extensions_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|dir| load_manifest(&dir.path()))
+        .map(|(manifest, dir)| {
+            let modified = fs::metadata(component_path(&manifest, &dir))
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            loaded
+                .iter()
+                .find(|extension| {
+                    extension.manifest == manifest
+                        && extension.dir == dir
+                        && extension.modified == modified
+                })
+                .cloned()
+                .unwrap_or_else(|| {
+                    Arc::new(WasmExtension {
+                        manifest,
+                        dir,
+                        modified,
+                        instance: Mutex::new(None),
+                        starts: Mutex::new(vec![]),
+                    })
+                })
+        })
+        .collect();
+
+    if let Ok(mut loaded) = EXTENSIONS.lock() {
+        *loaded = extensions.clone();
+    }
+    extensions
+}
+
+/// The component file of an extension
+fn component_path(manifest: &Manifest, dir: &Path) -> PathBuf {
+    dir.join(manifest.component.as_deref().unwrap_or("extension.wasm"))
+}
+
+/// Read the manifest in an extension's directory
+fn load_manifest(dir: &Path) -> Option<(Manifest, PathBuf)> {
+    let path = dir.join("extension.toml");
+    let content = fs::read_to_string(&path).ok()?;
+    match toml::from_str(&content) {
+        Ok(manifest) => Some((manifest, dir.to_path_buf())),
+        Err(error) => {
+            log_error(&format!("Couldn't read {}: {error}\n", path.display()));
+            None
+        }
+    }
+}
+
+/// Run an item of a WASM extension, without waiting for it
+pub fn run_item(name: &str, id: &str) {
+    let Some(extension) = EXTENSIONS.lock().ok().and_then(|extensions| {
+        extensions
+            .iter()
+            .find(|extension| extension.manifest.name == name)
+            .cloned()
+    }) else {
+        return;
+    };
+
+    let id = id.to_string();
+    thread::spawn(move || {
+        if let Err(error) = extension.run(&id) {
+            log_error(&format!("{error}\n"));
+        }
+    });
+}
@@ -0,0 +1,537 @@
+//! The file index, which lets the files and folders under the configured roots be searched
+//!
+//! The roots are walked in the background, leaving out hidden files, the paths that match the
+//! excluded globs, and the paths that `.gitignore` and `.ignore` files ignore. The index is kept
+//! up to date by the file system events of the roots (FSEvents on macOS), and only the folders
+//! that the events are about are read again. Without events (or when some were dropped), the
+//! modification times of the folders and of their ignore files are checked instead.
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, Once, RwLock, mpsc},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use iced::widget::image::Handle;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{
+    config::FileSearch,
+    shell::expand_home,
+    utils::{self, handle_from_icns, log_error},
+};
+
+/// How often the folders are checked for changes, if there are no file system events
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the settings are checked for changes between events, along with the roots that
+/// didn't exist yet
+const SETTINGS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the events of a burst of changes (like a checkout) are gathered for, before the
+/// folders are read again
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// The files in each folder that ignore rules are read from
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// The extensions of folders that macOS shows as files, whose contents aren't searched
+const BUNDLES: [&str; 10] = [
+    "app",
+    "bundle",
+    "framework",
+    "key",
+    "numbers",
+    "pages",
+    "photoslibrary",
+    "rtfd",
+    "xcodeproj",
+    "xcworkspace",
+];
+
+/// Where the icons for the kinds of files are
+const ICONS_PATH: &str = "/System/Library/CoreServices/CoreTypes.bundle/Contents/Resources";
+
+/// The index, which is shared between the thread that keeps it up to date and the provider
+static INDEX: LazyLock<RwLock<FileIndex>> = LazyLock::new(Default::default);
+
+/// The settings that the index is kept up to date with, which change when the config is reloaded
+static SETTINGS: Mutex<Option<FileSearch>> = Mutex::new(None);
+
+/// A file or folder in the index
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub kind: FileKind,
+    name_lc: String,
+}
+
+/// The kinds of files that have their own icons
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileKind {
+    Folder,
+    Executable,
+    Document,
+}
+
+/// A line of an ignore file, or an excluded glob from the config
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    glob: String,
+    /// Whether the rule brings back paths that an earlier rule ignored (`!pattern`)
+    negated: bool,
+    /// Whether the rule only matches folders (`pattern/`)
+    dir_only: bool,
+    /// Whether the glob is matched against the path from `base`, instead of the name
+    anchored: bool,
+    base: PathBuf,
+}
+
+/// A folder that was read, with what is needed to tell whether it changed
+#[derive(Debug, Clone)]
+struct Folder {
+    /// The modification times of the folder and of its ignore files
+    stamp: Vec<Option<SystemTime>>,
+    /// The rules of the folders above this one
+    inherited: Arc<Vec<Rule>>,
+    /// The inherited rules, and the ones of this folder's ignore files
+    rules: Arc<Vec<Rule>>,
+    entries: Vec<FileEntry>,
+    subfolders: Vec<PathBuf>,
+}
+
+/// The folders under the roots, by their paths
+#[derive(Debug, Default)]
+pub struct FileIndex {
+    settings: Option<FileSearch>,
+    folders: HashMap<PathBuf, Folder>,
+}
+
+impl FileIndex {
+    /// The entries whose names start with the query, ranked like the apps are
+    pub fn search(&self, query: &str, limit: usize) -> Vec<FileEntry> {
+        if query.is_empty() {
+            return vec![];
+        }
+        let entries = self
+            .folders
+            .values()
+            .flat_map(|folder| &folder.entries)
+            .map(|entry| (entry.name_lc.as_str(), entry));
+        utils::search_prefix(entries, query)
+            .into_iter()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Remove a folder and everything under it
+    fn remove_tree(&mut self, path: &Path) {
+        self.folders.retain(|folder, _| !folder.starts_with(path));
+    }
+}
+
+/// Keep the index up to date with the settings, starting the thread that does it the first time
+pub fn watch(settings: &FileSearch) {
+    if let Ok(mut current) = SETTINGS.lock() {
+        *current = Some(settings.clone());
+    }
+
+    static STARTED: Once = Once::new();
+    STARTED.call_once(|| {
+        thread::spawn(|| {
+            let (sender, events) = mpsc::channel();
+            let watcher = notify::recommended_watcher(sender)
+                .map_err(|error| {
+                    log_error(&format!("Unable to watch the file search roots: {error}"))
+                })
+                .ok();
+            match watcher {
+                Some(watcher) => follow_events(watcher, events),
+                None => loop {
+                    let settings = SETTINGS.lock().ok().and_then(|settings| settings.clone());
+                    if let Some(settings) = settings {
+                        refresh(&settings);
+                    }
+                    thread::sleep(REFRESH_INTERVAL);
+                },
+            }
+        });
+    });
+}
+
+/// Keep the index up to date with the events of the roots, reading only the folders that they are
+/// about again
+fn follow_events(
+    mut watcher: RecommendedWatcher,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+) {
+    let mut watched: Vec<PathBuf> = vec![];
+    loop {
+        let Some(settings) = SETTINGS.lock().ok().and_then(|settings| settings.clone()) else {
+            thread::sleep(SETTINGS_INTERVAL);
+            continue;
+        };
+        let roots = roots(&settings);
+        update_roots(&settings, &roots);
+
+        for root in &watched {
+            if !roots.iter().any(|(path, _)| path == root) {
+                watcher.unwatch(root).ok();
+            }
+        }
+        watched.retain(|root| roots.iter().any(|(path, _)| path == root));
+        for (root, _) in &roots {
+            if !watched.contains(root)
+                && root.is_dir()
+                && watcher.watch(root, RecursiveMode::Recursive).is_ok()
+            {
+                watched.push(root.clone());
+            }
+        }
+
+        let Ok(first) = events.recv_timeout(SETTINGS_INTERVAL) else {
+            continue;
+        };
+        thread::sleep(SETTLE_TIME);
+
+        let mut paths = HashSet::new();
+        let mut rescan = false;
+        for event in [first].into_iter().chain(events.try_iter()) {
+            match event {
+                Ok(event) => {
+                    rescan |= event.need_rescan();
+                    paths.extend(event.paths);
+                }
+                Err(_) => rescan = true,
+            }
+        }
+
+        // Events were dropped, so every folder is checked for whether it changed
+        if rescan {
+            refresh(&settings);
+        } else {
+            read_again(&paths);
+        }
+    }
+}
+
+/// Search the index, which is empty until the roots were first walked
+pub fn search(query: &str, limit: usize) -> Vec<FileEntry> {
+    INDEX
+        .read()
+        .map(|index| index.search(query, limit))
+        .unwrap_or_default()
+}
+
+/// The icon of a kind of file, from the icons that macOS uses
+pub fn icon(kind: FileKind) -> Option<Handle> {
+    static ICONS: LazyLock<[Option<Handle>; 3]> = LazyLock::new(|| {
+        [
+            "GenericFolderIcon",
+            "ExecutableBinaryIcon",
+            "GenericDocumentIcon",
+        ]
+        .map(|name| handle_from_icns(Path::new(&format!("{ICONS_PATH}/{name}.icns"))))
+    });
+    match kind {
+        FileKind::Folder => ICONS[0].clone(),
+        FileKind::Executable => ICONS[1].clone(),
+        FileKind::Document => ICONS[2].clone(),
+    }
+}
+
+/// The roots of the settings, with the rules of the globs that they exclude
+fn roots(settings: &FileSearch) -> Vec<(PathBuf, Arc<Vec<Rule>>)> {
+    settings
+        .roots
+        .iter()
+        .map(|root| {
+            let root = PathBuf::from(expand_home(root));
+            let excluded = settings
+                .exclude
+                .iter()
+                .filter_map(|glob| parse_rule(glob, &root))
+                .collect();
+            (root, Arc::new(excluded))
+        })
+        .collect()
+}
+
+/// Walk the roots again if the settings changed, or else walk the roots that didn't exist when
+/// the index was built but do now
+fn update_roots(settings: &FileSearch, roots: &[(PathBuf, Arc<Vec<Rule>>)]) {
+    let rebuild = INDEX
+        .read()
+        .map(|index| index.settings.as_ref() != Some(settings))
+        .unwrap_or(true);
+
+    if rebuild {
+        let mut folders = HashMap::new();
+        for (root, excluded) in roots {
+            walk(root, excluded.clone(), &mut folders);
+        }
+        if let Ok(mut index) = INDEX.write() {
+            *index = FileIndex {
+                settings: Some(settings.clone()),
+                folders,
+            };
+        }
+        return;
+    }
+
+    let missing = INDEX
+        .read()
+        .map(|index| {
+            roots
+                .iter()
+                .filter(|(root, _)| !index.folders.contains_key(root) && root.is_dir())
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    for (root, excluded) in missing {
+        let mut walked = HashMap::new();
+        walk(&root, excluded, &mut walked);
+        if let Ok(mut index) = INDEX.write() {
+            index.folders.extend(walked);
+        }
+    }
+}
+
+/// Update the roots, and read the folders whose modification times changed again
+fn refresh(settings: &FileSearch) {
+    update_roots(settings, &roots(settings));
+    let changed = INDEX
+        .read()
+        .map(|index| {
+            index
+                .folders
+                .iter()
+                .filter(|(path, folder)| stamp(path) != folder.stamp)
+                .map(|(path, folder)| (path.clone(), folder.clone()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    read_changed(changed);
+}
+
+/// Read the folders that events were about again, which are the folders that the paths are in (or
+/// the paths themselves, for the folders that changed)
+fn read_again(paths: &HashSet<PathBuf>) {
+    let changed = INDEX
+        .read()
+        .map(|index| {
+            paths
+                .iter()
+                .flat_map(|path| [Some(path.as_path()), path.parent()])
+                .flatten()
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .filter_map(|path| Some((path.to_path_buf(), index.folders.get(path)?.clone())))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    read_changed(changed);
+}
+
+/// Read folders again, along with the folders under them that are new or whose rules changed
+fn read_changed(changed: Vec<(PathBuf, Folder)>) {
+    for (path, old) in changed {
+        let Some(folder) = read_folder(&path, old.inherited.clone()) else {
+            if let Ok(mut index) = INDEX.write() {
+                index.remove_tree(&path);
+            }
+            continue;
+        };
+
+        // The folders under this one are only walked again if they are new, or if the rules
+        // that they inherit changed
+        let rules_changed = folder.rules != old.rules;
+        let mut walked = HashMap::new();
+        for subfolder in &folder.subfolders {
+            if rules_changed || !old.subfolders.contains(subfolder) {
+                walk(subfolder, folder.rules.clone(), &mut walked);
+            }
+        }
+
+        if let Ok(mut index) = INDEX.write() {
+            // A folder above this one could have been read again in the meantime
+            if index.folders.get(&path).is_none_or(|current| {
+                current.stamp != old.stamp || !Arc::ptr_eq(&current.inherited, &old.inherited)
+            }) {
+                continue;
+            }
+            for subfolder in &old.subfolders {
+                if rules_changed || !folder.subfolders.contains(subfolder) {
+                    index.remove_tree(subfolder);
+                }
+            }
+            index.folders.extend(walked);
+            index.folders.insert(path, folder);
+        }
+    }
+}
+
+/// Read a folder and all the folders under it
+fn walk(root: &Path, inherited: Arc<Vec<Rule>>, folders: &mut HashMap<PathBuf, Folder>) {
+    let mut stack = vec![(root.to_path_buf(), inherited)];
+    while let Some((path, inherited)) = stack.pop() {
+        let Some(folder) = read_folder(&path, inherited) else {
+            continue;
+        };
+        stack.extend(
+            folder
+                .subfolders
+                .iter()
+                .map(|subfolder| (subfolder.clone(), folder.rules.clone())),
+        );
+        folders.insert(path, folder);
+    }
+}
+
+/// Read the entries of a folder that aren't hidden or ignored
+fn read_folder(path: &Path, inherited: Arc<Vec<Rule>>) -> Option<Folder> {
+    let stamp = stamp(path);
+    stamp.first().copied().flatten()?;
+
+    let mut rules = inherited.as_ref().clone();
+    for file in IGNORE_FILES {
+        if let Ok(content) = fs::read_to_string(path.join(file)) {
+            rules.extend(content.lines().filter_map(|line| parse_rule(line, path)));
+        }
+    }
+
+    let mut entries = vec![];
+    let mut subfolders = vec![];
+    for entry in fs::read_dir(path).ok()?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let is_dir = file_type.is_dir();
+        let entry_path = entry.path();
+        if name.starts_with('.') || is_ignored(&entry_path, &name, is_dir, &rules) {
+            continue;
+        }
+
+        // Bundles like apps are shown as files, without the files inside of them
+        let extension = name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase());
+        let is_bundle = is_dir && extension.is_some_and(|extension| BUNDLES.contains(&&*extension));
+        let kind = if is_dir && !is_bundle {
+            subfolders.push(entry_path.clone());
+            FileKind::Folder
+        } else if name.ends_with(".app") || is_executable(&entry) {
+            FileKind::Executable
+        } else {
+            FileKind::Document
+        };
+        entries.push(FileEntry {
+            path: entry_path,
+            name_lc: name.to_lowercase(),
+            name,
+            kind,
+        });
+    }
+
+    Some(Folder {
+        stamp,
+        inherited,
+        rules: Arc::new(rules),
+        entries,
+        subfolders,
+    })
+}
+
+/// The modification times that tell whether a folder has to be read again
+fn stamp(path: &Path) -> Vec<Option<SystemTime>> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    [modified(path)]
+        .into_iter()
+        .chain(IGNORE_FILES.map(|file| modified(&path.join(file))))
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(entry: &fs::DirEntry) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    entry
+        .metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_: &fs::DirEntry) -> bool {
+    false
+}
+
+/// Turn a line of an ignore file into a rule, if it isn't empty or a comment
+fn parse_rule(line: &str, base: &Path) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, glob) = match line.strip_prefix('!') {
+        Some(glob) => (true, glob),
+        None => (false, line),
+    };
+    let (dir_only, glob) = match glob.strip_suffix('/') {
+        Some(glob) => (true, glob),
+        None => (false, glob),
+    };
+    Some(Rule {
+        anchored: glob.contains('/'),
+        glob: glob.trim_start_matches('/').to_string(),
+        negated,
+        dir_only,
+        base: base.to_path_buf(),
+    })
+}
+
+/// Whether the last rule that matches a path ignores it, like in `.gitignore` files
+fn is_ignored(path: &Path, name: &str, is_dir: bool, rules: &[Rule]) -> bool {
+    let mut ignored = false;
+    for rule in rules {
+        if rule.dir_only && !is_dir {
+            continue;
+        }
+        let matched = if rule.anchored {
+            path.strip_prefix(&rule.base).is_ok_and(|relative| {
+                glob_match(rule.glob.as_bytes(), relative.to_string_lossy().as_bytes())
+            })
+        } else {
+            glob_match(rule.glob.as_bytes(), name.as_bytes())
+        };
+        if matched {
+            ignored = !rule.negated;
+        }
+    }
+    ignored
+}
+
+/// Whether a glob matches the text, where `*` and `?` don't match a `/`, and `**` matches any
+/// number of folders
+fn glob_match(glob: &[u8], text: &[u8]) -> bool {
+    match (glob.first(), text.first()) {
+        (Some(b'*'), _) if glob.starts_with(b"**") => {
+            let rest = &glob[2..];
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len())
+                .filter(|&i| i == 0 || i == text.len() || text[i - 1] == b'/')
+                .any(|i| glob_match(rest, &text[i..]))
+        }
+        (Some(b'*'), _) => {
+            glob_match(&glob[1..], text)
+                || (text.first().is_some_and(|&c| c != b'/') && glob_match(glob, &text[1..]))
+        }
+        (Some(b'?'), Some(&c)) if c != b'/' => glob_match(&glob[1..], &text[1..]),
+        (Some(a), Some(b)) if a == b => glob_match(&glob[1..], &text[1..]),
+        (None, None) => true,
+        _ => false,
+    }
+}
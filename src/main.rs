@@ -1,12 +1,21 @@
 mod app;
+mod bookmarks;
 mod calculator;
 mod clipboard;
 mod commands;
 mod config;
+mod currency;
+mod date_calculator;
+mod extensions;
+mod files;
 mod platform;
+mod recent;
+mod scripts;
+mod shell;
 mod styles;
 mod unit_conversion;
 mod utils;
+mod world_clock;
 
 use std::path::Path;
 
@@ -20,6 +20,7 @@ use std::{
     sync::LazyLock,
 };
 
+use objc2_app_kit::NSWorkspace;
 use objc2_core_foundation::{CFArray, CFRetained, CFURL};
 use objc2_foundation::{NSBundle, NSNumber, NSString, NSURL, ns_string};
 use rayon::iter::{IntoParallelIterator, ParallelIterator as _};
@@ -307,3 +308,13 @@ fn is_helper_location(path: &Path) -> bool {
         || s.contains("/Contents/Frameworks/")
         || s.contains("/Library/PrivilegedHelperTools/")
 }
+
+/// The apps that Launch Services says can open a file, with its default app first
+pub(crate) fn apps_to_open(path: &str, store_icons: bool) -> Vec<App> {
+    let url = NSURL::fileURLWithPath(&NSString::from_str(path));
+    NSWorkspace::sharedWorkspace()
+        .URLsForApplicationsToOpenURL(&url)
+        .iter()
+        .filter_map(|app| query_app(app, store_icons))
+        .collect()
+}
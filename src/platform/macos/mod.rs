@@ -4,7 +4,7 @@ mod haptics;
 
 use iced::wgpu::rwh::WindowHandle;
 
-pub(super) use self::discovery::get_installed_apps;
+pub(super) use self::discovery::{apps_to_open, get_installed_apps};
 pub(super) use self::haptics::perform_haptic;
 
 /// This sets the activation policy of the app to Accessory, allowing rustcast to be visible ontop
@@ -89,3 +89,34 @@ pub(super) fn transform_process_to_ui_element() -> u32 {
         )
     }
 }
+
+/// This gets the text that is selected in the focused app, through the accessibility API
+///
+/// Returns None if nothing is selected, or if rustcast isn't trusted for accessibility
+pub(super) fn selected_text() -> Option<String> {
+    use objc2_application_services::{AXError, AXUIElement};
+    use objc2_core_foundation::{CFRetained, CFString, CFType};
+    use std::ptr::{self, NonNull};
+
+    fn attribute(element: &AXUIElement, name: &'static str) -> Option<CFRetained<CFType>> {
+        let mut value: *const CFType = ptr::null();
+        let error = unsafe {
+            element
+                .copy_attribute_value(&CFString::from_static_str(name), NonNull::from(&mut value))
+        };
+        if error != AXError::Success {
+            return None;
+        }
+        NonNull::new(value.cast_mut()).map(|value| unsafe { CFRetained::from_raw(value) })
+    }
+
+    let system = unsafe { AXUIElement::new_system_wide() };
+    let focused = attribute(&system, "AXFocusedUIElement")?
+        .downcast::<AXUIElement>()
+        .ok()?;
+    let selected = attribute(&focused, "AXSelectedText")?
+        .downcast::<CFString>()
+        .ok()?
+        .to_string();
+    (!selected.is_empty()).then_some(selected)
+}
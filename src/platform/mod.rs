@@ -27,6 +27,15 @@ pub fn transform_process_to_ui_element() {
     self::macos::transform_process_to_ui_element();
 }
 
+/// The text that is selected in the focused app, if there is any
+pub fn selected_text() -> Option<String> {
+    #[cfg(target_os = "macos")]
+    return self::macos::selected_text();
+
+    #[cfg(not(target_os = "macos"))]
+    None
+}
+
 /// The kinds of haptic patterns that can be performed
 #[allow(dead_code)]
 #[derive(Copy, Clone, Debug)]
@@ -46,6 +55,17 @@ pub fn perform_haptic(_: HapticPattern) -> bool {
     false
 }
 
+/// The apps that can open a file, with its default app first
+#[cfg(target_os = "macos")]
+pub fn apps_to_open(path: &str, store_icons: bool) -> Vec<App> {
+    self::macos::apps_to_open(path, store_icons)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn apps_to_open(_path: &str, _store_icons: bool) -> Vec<App> {
+    vec![]
+}
+
 #[cfg(target_os = "macos")]
 pub fn get_installed_apps(store_icons: bool) -> Vec<App> {
     self::macos::get_installed_apps(store_icons)
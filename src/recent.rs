@@ -0,0 +1,203 @@
+//! Recently used files and folders, from the list that the platform keeps
+//!
+//! On macOS this is the last used date that Spotlight keeps for each file (read with `mdfind`),
+//! and elsewhere it is the freedesktop `recently-used.xbel` file that GTK and KDE apps write to.
+//! The list is read again in the background every so often, so that searching it never has to
+//! wait for `mdfind`.
+use std::{
+    collections::HashSet,
+    fs,
+    path::PathBuf,
+    sync::{Once, RwLock},
+    thread,
+    time::Duration,
+};
+
+use jiff::Timestamp;
+
+use crate::files::FileKind;
+
+/// How often the list is read again
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How many of the most recent files are kept
+const MAX_FILES: usize = 100;
+
+/// The list, starting with the most recently used file
+static RECENT: RwLock<Vec<RecentFile>> = RwLock::new(Vec::new());
+
+/// A file or folder that was used recently
+#[derive(Debug, Clone)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub kind: FileKind,
+    name_lc: String,
+}
+
+/// Keep the list up to date, starting the thread that does it the first time
+pub fn watch() {
+    static STARTED: Once = Once::new();
+    STARTED.call_once(|| {
+        thread::spawn(|| {
+            loop {
+                let files = read();
+                if let Ok(mut recent) = RECENT.write() {
+                    *recent = files;
+                }
+                thread::sleep(REFRESH_INTERVAL);
+            }
+        });
+    });
+}
+
+/// The recent files whose names have every word of the query in them, most recent first
+///
+/// An empty query gives the most recent files
+pub fn search(query: &str, limit: usize) -> Vec<RecentFile> {
+    let words = query.split_whitespace().collect::<Vec<_>>();
+    RECENT
+        .read()
+        .map(|recent| {
+            recent
+                .iter()
+                .filter(|file| words.iter().all(|word| file.name_lc.contains(word)))
+                .take(limit)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Read the platform's list, leaving out the files that don't exist anymore
+fn read() -> Vec<RecentFile> {
+    let mut used = read_list();
+    used.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut seen = HashSet::new();
+    used.into_iter()
+        .filter(|(path, _)| seen.insert(path.clone()))
+        .filter_map(|(path, _)| {
+            let meta = fs::metadata(&path).ok()?;
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            Some(RecentFile {
+                kind: if meta.is_dir() {
+                    FileKind::Folder
+                } else {
+                    FileKind::Document
+                },
+                name_lc: name.to_lowercase(),
+                name,
+                path,
+            })
+        })
+        .take(MAX_FILES)
+        .collect()
+}
+
+/// The files that Spotlight says were used in the last month, and when
+#[cfg(target_os = "macos")]
+fn read_list() -> Vec<(PathBuf, Timestamp)> {
+    const ATTRIBUTE: &str = "kMDItemLastUsedDate";
+
+    let home = std::env::var("HOME").unwrap_or_default();
+    let Ok(output) = std::process::Command::new("mdfind")
+        .args([
+            "-onlyin",
+            &home,
+            "-attr",
+            ATTRIBUTE,
+            "kMDItemLastUsedDate >= $time.today(-30) && \
+             kMDItemContentType != com.apple.application-bundle",
+        ])
+        .output()
+    else {
+        return vec![];
+    };
+
+    // Each line is the path, then `kMDItemLastUsedDate = 2024-05-01 09:30:00 +0000`
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (path, date) = line.rsplit_once(&format!(" {ATTRIBUTE} = "))?;
+            let used = jiff::fmt::strtime::parse("%Y-%m-%d %H:%M:%S %z", date.trim())
+                .ok()?
+                .to_timestamp()
+                .ok()?;
+            Some((PathBuf::from(path.trim_end()), used))
+        })
+        .collect()
+}
+
+/// The files in `recently-used.xbel`, and when each one was last used
+#[cfg(not(target_os = "macos"))]
+fn read_list() -> Vec<(PathBuf, Timestamp)> {
+    let data_home = std::env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| crate::shell::expand_home("~/.local/share"));
+    let Ok(content) = fs::read_to_string(PathBuf::from(data_home).join("recently-used.xbel"))
+    else {
+        return vec![];
+    };
+
+    content
+        .split("<bookmark")
+        .skip(1)
+        .filter_map(|bookmark| {
+            // The attributes of the tag, which start with a space (unlike `<bookmark:...>` tags)
+            let tag = &bookmark[..bookmark.find('>')?];
+            let path = unescape(attribute(tag, "href")?)
+                .strip_prefix("file://")
+                .map(percent_decode)?;
+            let used = ["visited", "modified", "added"]
+                .into_iter()
+                .filter_map(|name| attribute(tag, name)?.parse::<Timestamp>().ok())
+                .max()?;
+            Some((PathBuf::from(path), used))
+        })
+        .collect()
+}
+
+/// The value of an attribute of an XML tag
+#[cfg(not(target_os = "macos"))]
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {name}=\""))? + name.len() + 3;
+    let end = tag[start..].find('"')?;
+    Some(&tag[start..start + end])
+}
+
+/// Turn the entities of an XML attribute back into the characters that they stand for
+#[cfg(not(target_os = "macos"))]
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Decode the `%20`s of a file URL's path
+#[cfg(not(target_os = "macos"))]
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
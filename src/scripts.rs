@@ -0,0 +1,228 @@
+//! Script commands, which are the executable scripts in `~/.config/rustcast/scripts`
+//!
+//! The details of a script are read from the comments at the top of it, like:
+//!
+//! ```sh
+//! #!/bin/bash
+//! # @rustcast.title Deploy
+//! # @rustcast.alias deploy
+//! # @rustcast.icon ~/icons/deploy.png
+//! # @rustcast.description Deploys a branch to an environment
+//! # @rustcast.argument branch
+//! # @rustcast.argument environment
+//! # @rustcast.output inline
+//! ```
+//!
+//! Every field is optional, and the title is the file name if there isn't one. The arguments are
+//! the words typed after the alias, and a form asks for the ones that weren't typed before the
+//! script runs. The output is one of the shell output modes.
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Read,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    app::{
+        Message,
+        apps::{App, AppCommand},
+        pages::generic::{Field, Input, View},
+    },
+    config::{OutputMode, Shelly},
+    shell::quote,
+};
+
+/// How much of a script is read to find its header
+const HEADER_BYTES: u64 = 4096;
+
+/// The start of the id of the form that asks for the arguments of a script, which is followed by
+/// the script's path
+pub const ARGUMENTS_FORM: &str = "script:";
+
+/// A script command, with the details from its header
+#[derive(Debug, Clone)]
+pub struct Script {
+    pub path: PathBuf,
+    pub title: String,
+    pub alias: String,
+    pub icon_path: Option<String>,
+    pub description: Option<String>,
+    pub arguments: Vec<String>,
+    pub output: OutputMode,
+}
+
+impl Script {
+    /// Read the header of a script
+    fn parse(path: &Path, header: &str) -> Script {
+        let mut fields = BTreeMap::new();
+        let mut arguments = vec![];
+        for line in header.lines() {
+            let line = line.trim();
+            let Some(comment) = ["#", "//", "--"]
+                .iter()
+                .find_map(|prefix| line.strip_prefix(prefix))
+            else {
+                if line.is_empty() {
+                    continue;
+                }
+                // The header ends at the first line of code
+                break;
+            };
+
+            let Some((key, value)) = comment
+                .trim()
+                .strip_prefix("@rustcast.")
+                .and_then(|field| field.split_once(char::is_whitespace))
+            else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key {
+                "argument" => arguments.push(value),
+                key => {
+                    fields.insert(key.to_string(), value);
+                }
+            }
+        }
+
+        let title = fields.remove("title").unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        Script {
+            path: path.to_path_buf(),
+            alias: fields.remove("alias").unwrap_or(title.clone()),
+            title,
+            icon_path: fields.remove("icon"),
+            description: fields.remove("description"),
+            arguments,
+            output: match fields.remove("output").as_deref() {
+                Some("notify") => OutputMode::Notify,
+                Some("inline") => OutputMode::Inline,
+                Some("copy") => OutputMode::Copy,
+                _ => OutputMode::Silent,
+            },
+        }
+    }
+
+    /// The shell command that runs the script, with the words after its alias as the arguments
+    pub fn to_shelly(&self) -> Shelly {
+        Shelly {
+            command: quote(&self.path.to_string_lossy()),
+            icon_path: self.icon_path.clone(),
+            alias: self.title.clone(),
+            alias_lc: self.alias.to_lowercase(),
+            cwd: self
+                .path
+                .parent()
+                .map(|dir| dir.to_string_lossy().to_string()),
+            env: BTreeMap::new(),
+            shell: None,
+            timeout: None,
+            terminal: None,
+            output: self.output,
+        }
+    }
+
+    /// Converts the script to an app so that it can be added to the app list, by running it like
+    /// a shell command
+    ///
+    /// A script with arguments asks for the ones that weren't typed, with
+    /// [`Message::PromptArguments`]
+    pub fn to_app(&self) -> App {
+        let shell_app = self.to_shelly().to_app();
+        let mut desc = self
+            .description
+            .clone()
+            .unwrap_or("Script Command".to_string());
+        if !self.arguments.is_empty() {
+            let arguments = self
+                .arguments
+                .iter()
+                .map(|argument| format!("<{argument}>"))
+                .collect::<Vec<_>>();
+            desc = format!("{desc} · {}", arguments.join(" "));
+        }
+
+        let open_command = if self.arguments.is_empty() {
+            shell_app.open_command
+        } else {
+            AppCommand::Message(Message::PromptArguments(
+                self.path.to_string_lossy().to_string(),
+            ))
+        };
+        App {
+            open_command,
+            desc,
+            ..shell_app
+        }
+    }
+
+    /// A form with an input for each argument, filled in with the words that were typed
+    pub fn arguments_view(&self, typed: &[String]) -> View {
+        View::Form {
+            title: self.title.clone(),
+            id: format!("{ARGUMENTS_FORM}{}", self.path.to_string_lossy()),
+            fields: self
+                .arguments
+                .iter()
+                .enumerate()
+                .map(|(index, argument)| Field {
+                    id: format!("arg{}", index + 1),
+                    label: argument.clone(),
+                    input: Input::Text {
+                        placeholder: argument.clone(),
+                        value: typed.get(index).cloned().unwrap_or_default(),
+                    },
+                })
+                .collect(),
+            extension: None,
+        }
+    }
+}
+
+/// The directory that scripts are read from
+pub fn scripts_dir() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap_or_default() + "/.config/rustcast/scripts")
+}
+
+/// The executable files in the scripts directory, with when they were last changed
+///
+/// This is compared to the last one to tell whether the scripts need to be read again
+pub fn scan() -> Vec<(PathBuf, SystemTime)> {
+    let mut scripts = fs::read_dir(scripts_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let executable = metadata.is_file() && metadata.permissions().mode() & 0o111 != 0;
+            executable.then_some((entry.path(), metadata.modified().ok()?))
+        })
+        .collect::<Vec<_>>();
+    scripts.sort();
+    scripts
+}
+
+/// Read every script in the scripts directory
+pub fn load_scripts() -> Vec<Script> {
+    scan()
+        .into_iter()
+        .filter_map(|(path, _)| read_script(&path))
+        .collect()
+}
+
+/// Read the header of the script at a path
+pub fn read_script(path: &Path) -> Option<Script> {
+    let mut header = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(HEADER_BYTES)
+        .read_to_end(&mut header)
+        .ok()?;
+    Some(Script::parse(path, &String::from_utf8_lossy(&header)))
+}
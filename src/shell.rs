@@ -0,0 +1,390 @@
+//! Running the shell commands from the config, with their arguments filled in and quoted
+use std::{
+    fs,
+    io::Read,
+    os::unix::fs::PermissionsExt,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use arboard::Clipboard;
+use iced::futures::{SinkExt, Stream};
+use iced::stream;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::{
+    config::{OutputMode, Shelly},
+    utils::{log_error, show_notification, text_after_alias},
+};
+
+/// What a command that shows its output inline sends back while it runs
+#[derive(Debug, Clone)]
+pub enum ShellEvent {
+    /// A line that the command printed, to stdout or stderr
+    Line(String),
+    /// The command exited, with its exit code if it wasn't killed by a signal
+    Finished(Option<i32>),
+    /// The command ran for longer than its timeout, and was stopped
+    TimedOut,
+}
+
+/// Run a shell command, with the text after its alias in the query as the arguments
+///
+/// Commands that show their output inline are run with [`stream_output`] instead, which this falls
+/// back to running silently
+pub fn run(shelly: &Shelly, query: &str) {
+    let command = fill_template(&shelly.command, text_after_alias(query, &shelly.alias_lc));
+
+    if let Some(terminal) = &shelly.terminal {
+        run_in_terminal(shelly, &command, terminal);
+        return;
+    }
+
+    let capture = matches!(shelly.output, OutputMode::Copy | OutputMode::Notify);
+    let mut process = process(shelly, &command);
+    if capture {
+        process.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut child = match process.spawn() {
+        Ok(child) => child,
+        Err(error) => {
+            log_error(&format!("Couldn't run \"{command}\": {error}"));
+            return;
+        }
+    };
+
+    let timeout = shelly.timeout.map(Duration::from_secs);
+    if !capture && timeout.is_none() {
+        return;
+    }
+
+    let output = shelly.output;
+    let title = shelly.alias.clone();
+    thread::spawn(move || {
+        let stdout = child
+            .stdout
+            .take()
+            .map(|out| thread::spawn(|| read_all(out)));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|err| thread::spawn(|| read_all(err)));
+
+        let start = Instant::now();
+        let code = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status.code(),
+                Ok(None) if timeout.is_some_and(|timeout| start.elapsed() > timeout) => {
+                    child.kill().ok();
+                    child.wait().ok();
+                    log_error(&format!("\"{command}\" was stopped after {timeout:?}"));
+                    break None;
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(50)),
+                Err(_) => break None,
+            }
+        };
+        let duration = start.elapsed();
+        let stdout = stdout
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+        let stderr = stderr
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+
+        match output {
+            OutputMode::Copy => {
+                Clipboard::new()
+                    .and_then(|mut clipboard| clipboard.set_text(stdout.trim_end()))
+                    .ok();
+                show_notification(
+                    &title,
+                    &format!("{}, output copied", describe_exit(code, duration)),
+                );
+            }
+            OutputMode::Notify => {
+                let last_line = stdout
+                    .lines()
+                    .chain(stderr.lines())
+                    .rfind(|line| !line.trim().is_empty())
+                    .unwrap_or("");
+                show_notification(
+                    &title,
+                    &format!("{}\n{}", describe_exit(code, duration), last_line),
+                );
+            }
+            OutputMode::Silent | OutputMode::Inline => {}
+        }
+    });
+}
+
+/// Run a shell command, and stream what it prints as it runs
+///
+/// The command is killed when the stream is dropped, so aborting the task that runs the stream
+/// cancels the command
+pub fn stream_output(shelly: Shelly, query: String) -> impl Stream<Item = ShellEvent> {
+    stream::channel(100, async move |mut output| {
+        let command = fill_template(&shelly.command, text_after_alias(&query, &shelly.alias_lc));
+        let mut process = tokio::process::Command::from(process(&shelly, &command));
+        process
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = match process.spawn() {
+            Ok(child) => child,
+            Err(error) => {
+                let _ = output
+                    .send(ShellEvent::Line(format!(
+                        "Couldn't run \"{command}\": {error}"
+                    )))
+                    .await;
+                let _ = output.send(ShellEvent::Finished(None)).await;
+                return;
+            }
+        };
+
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return;
+        };
+        let mut stdout = BufReader::new(stdout).lines();
+        let mut stderr = BufReader::new(stderr).lines();
+        let mut lines = output.clone();
+        let run = async {
+            let (mut stdout_done, mut stderr_done) = (false, false);
+            while !(stdout_done && stderr_done) {
+                tokio::select! {
+                    line = stdout.next_line(), if !stdout_done => match line {
+                        Ok(Some(line)) => {
+                            let _ = lines.send(ShellEvent::Line(line)).await;
+                        }
+                        _ => stdout_done = true,
+                    },
+                    line = stderr.next_line(), if !stderr_done => match line {
+                        Ok(Some(line)) => {
+                            let _ = lines.send(ShellEvent::Line(line)).await;
+                        }
+                        _ => stderr_done = true,
+                    },
+                }
+            }
+            child.wait().await.ok().and_then(|status| status.code())
+        };
+
+        let event = match shelly.timeout {
+            Some(timeout) => tokio::time::timeout(Duration::from_secs(timeout), run)
+                .await
+                .map_or(ShellEvent::TimedOut, ShellEvent::Finished),
+            None => ShellEvent::Finished(run.await),
+        };
+        let _ = output.send(event).await;
+    })
+}
+
+/// How a command exited, like "Finished in 1.2s"
+pub fn describe_exit(code: Option<i32>, duration: Duration) -> String {
+    let duration = format!("{:.1}s", duration.as_secs_f64());
+    match code {
+        Some(0) => format!("Finished in {duration}"),
+        Some(code) => format!("Exited with code {code} after {duration}"),
+        None => format!("Stopped after {duration}"),
+    }
+}
+
+/// The process for a command, run by the command's shell in its directory and environment
+fn process(shelly: &Shelly, command: &str) -> Command {
+    let mut process = Command::new(shelly.shell.as_deref().unwrap_or("sh"));
+    process.arg("-c").arg(command).envs(&shelly.env);
+    if let Some(cwd) = &shelly.cwd {
+        process.current_dir(expand_home(cwd));
+    }
+    process
+}
+
+/// Read everything from a pipe of a process
+fn read_all(mut pipe: impl Read) -> String {
+    let mut text = String::new();
+    pipe.read_to_string(&mut text).ok();
+    text
+}
+
+/// Run the command in a terminal app, through a `.command` script that the terminal opens
+fn run_in_terminal(shelly: &Shelly, command: &str, terminal: &str) {
+    let mut script = String::from("#!/bin/sh\n");
+    if let Some(cwd) = &shelly.cwd {
+        script += &format!("cd {}\n", quote(&expand_home(cwd)));
+    }
+    for (name, value) in &shelly.env {
+        script += &format!("export {}={}\n", name, quote(value));
+    }
+    script += &format!(
+        "exec {} -c {}\n",
+        quote(shelly.shell.as_deref().unwrap_or("sh")),
+        quote(command)
+    );
+
+    let path = std::env::temp_dir().join(format!(
+        "rustcast-{}.command",
+        shelly.alias_lc.replace(|c: char| !c.is_alphanumeric(), "-")
+    ));
+    let written = fs::write(&path, script)
+        .and_then(|_| fs::set_permissions(&path, fs::Permissions::from_mode(0o755)));
+    if let Err(error) = written {
+        log_error(&format!("Couldn't write {}: {error}", path.display()));
+        return;
+    }
+
+    Command::new("open")
+        .arg("-a")
+        .arg(terminal)
+        .arg(&path)
+        .spawn()
+        .ok();
+}
+
+/// Fill the arguments into a command, quoting each of them
+///
+/// `{args}` is every word, `{arg1}`, `{arg2}`, etc are the words by themselves, `{query}` is all
+/// of the text as one argument and `{clipboard}` is the clipboard's text. A command without any of
+/// these gets the words added to the end.
+pub fn fill_template(template: &str, text: &str) -> String {
+    fill_placeholders(template, text, || {
+        Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .unwrap_or_default()
+    })
+}
+
+/// Fill the placeholders in one pass over the template, so that the text of an argument is never
+/// taken for a placeholder itself
+fn fill_placeholders(template: &str, text: &str, clipboard: impl Fn() -> String) -> String {
+    let args = split_args(text);
+    let quoted_args = args.iter().map(|arg| quote(arg)).collect::<Vec<_>>();
+    let mut clipboard_text = None;
+
+    let mut command = String::new();
+    let mut filled = false;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        command.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let value = match &rest[1..end] {
+            "args" => Some(quoted_args.join(" ")),
+            "query" => Some(quote(text)),
+            "clipboard" => Some(quote(clipboard_text.get_or_insert_with(&clipboard))),
+            name => name
+                .strip_prefix("arg")
+                .and_then(|number| number.parse::<usize>().ok())
+                .map(|number| {
+                    number
+                        .checked_sub(1)
+                        .and_then(|index| quoted_args.get(index))
+                        .cloned()
+                        .unwrap_or("''".to_string())
+                }),
+        };
+        match value {
+            Some(value) => {
+                command.push_str(&value);
+                filled = true;
+                rest = &rest[end + 1..];
+            }
+            None => {
+                command.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    command.push_str(rest);
+
+    if !filled {
+        return [command.trim_end().to_string()]
+            .into_iter()
+            .chain(quoted_args)
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    command
+}
+
+/// Split text into words like a shell would, keeping quoted text together
+pub fn split_args(text: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current: Option<String> = None;
+    let mut quote_char = None;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote_char, c) {
+            (Some(q), c) if c == q => quote_char = None,
+            (None, '"' | '\'') => {
+                quote_char = Some(c);
+                current.get_or_insert_default();
+            }
+            (None, '\\') | (Some('"'), '\\') => {
+                if let Some(escaped) = chars.next() {
+                    current.get_or_insert_default().push(escaped);
+                }
+            }
+            (None, c) if c.is_whitespace() => args.extend(current.take()),
+            (_, c) => current.get_or_insert_default().push(c),
+        }
+    }
+    args.extend(current);
+    args
+}
+
+/// Quote text so that the shell takes it as one argument, as it is
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Replace a leading `~` with the home directory
+pub fn expand_home(path: &str) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) => std::env::var("HOME").unwrap_or_default() + rest,
+        None => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(template: &str, text: &str) -> String {
+        fill_placeholders(template, text, || "{args} x;id".to_string())
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(fill("echo", "a 'b c'"), "echo 'a' 'b c'");
+        assert_eq!(fill("echo {args}!", "a b"), "echo 'a' 'b'!");
+        assert_eq!(fill("echo {arg2} {arg1} {arg3}", "a b"), "echo 'b' 'a' ''");
+        assert_eq!(fill("echo {query}", "a  b"), "echo 'a  b'");
+        assert_eq!(fill("echo {x} {arg} {", "a"), "echo {x} {arg} { 'a'");
+        assert_eq!(fill("echo {x} {query} {", "a"), "echo {x} 'a' {");
+    }
+
+    #[test]
+    fn placeholders_in_arguments_are_not_filled() {
+        assert_eq!(
+            fill("echo {args} {query}", "{query} x;id"),
+            "echo '{query}' 'x;id' '{query} x;id'"
+        );
+        assert_eq!(fill("echo {arg1}", "{arg1}"), "echo '{arg1}'");
+        assert_eq!(
+            fill("echo {args}", "{arg1} {arg2}"),
+            "echo '{arg1}' '{arg2}'"
+        );
+        assert_eq!(
+            fill("echo {clipboard} {args}", "a"),
+            "echo '{args} x;id' 'a'"
+        );
+    }
+}
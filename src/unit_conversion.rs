@@ -1,4 +1,75 @@
 //! Unit conversion parsing and calculation.
+use std::collections::BTreeSet;
+use std::f64::consts::{PI, TAU};
+use std::fmt;
+use std::sync::{Mutex, RwLock};
+
+use crate::{
+    calculator::Expr,
+    config::CustomUnit,
+    currency::{self, CURRENCY_ALIASES, STALE_AFTER_DAYS},
+    utils::log_error,
+};
+
+/// Units that the user has defined in their config, see [`load_custom_units`]
+static CUSTOM_UNITS: RwLock<Vec<&'static UnitDef>> = RwLock::new(Vec::new());
+
+/// Currencies from the rates file, see [`load_currency_rates`]
+static CURRENCY_UNITS: RwLock<Vec<&'static UnitDef>> = RwLock::new(Vec::new());
+
+/// The date of the rates in [`CURRENCY_UNITS`]
+static RATES_DATE: RwLock<Option<String>> = RwLock::new(None);
+
+/// The names, aliases and units that were loaded from the config and the rates file
+///
+/// Units are `'static`, so these live for as long as rustcast does, but each distinct one is only
+/// kept once, so reloading the config doesn't use any more memory
+static INTERNED_NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+static INTERNED_ALIASES: Mutex<Vec<&'static [&'static str]>> = Mutex::new(Vec::new());
+static INTERNED_UNITS: Mutex<Vec<&'static UnitDef>> = Mutex::new(Vec::new());
+
+/// A `'static` copy of a name, which is shared with every other copy of the same name
+pub fn intern(name: &str) -> &'static str {
+    let Ok(mut names) = INTERNED_NAMES.lock() else {
+        return String::from(name).leak();
+    };
+    if let Some(interned) = names.get(name) {
+        return interned;
+    }
+    let interned: &'static str = String::from(name).leak();
+    names.insert(interned);
+    interned
+}
+
+/// A `'static` copy of a list of aliases, like [`intern`]
+pub fn intern_aliases(aliases: &[&str]) -> &'static [&'static str] {
+    let aliases = aliases
+        .iter()
+        .map(|alias| intern(alias))
+        .collect::<Vec<_>>();
+    let Ok(mut interned) = INTERNED_ALIASES.lock() else {
+        return aliases.leak();
+    };
+    if let Some(existing) = interned.iter().find(|existing| **existing == aliases) {
+        return existing;
+    }
+    let leaked: &'static [&'static str] = aliases.leak();
+    interned.push(leaked);
+    leaked
+}
+
+/// A `'static` copy of a unit, like [`intern`]
+fn intern_unit(unit: UnitDef) -> &'static UnitDef {
+    let Ok(mut interned) = INTERNED_UNITS.lock() else {
+        return Box::leak(Box::new(unit));
+    };
+    if let Some(existing) = interned.iter().find(|existing| ***existing == unit) {
+        return existing;
+    }
+    let leaked: &'static UnitDef = Box::leak(Box::new(unit));
+    interned.push(leaked);
+    leaked
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnitCategory {
@@ -6,9 +77,186 @@ pub enum UnitCategory {
     Mass,
     Volume,
     Temperature,
+    Duration,
+    Area,
+    Speed,
+    DigitalStorage,
+    Pressure,
+    Energy,
+    Power,
+    Angle,
+    FuelEconomy,
+    FuelConsumption,
+    Currency,
+    /// A category defined by the user in their config, like "story points"
+    Custom(&'static str),
+}
+
+impl UnitCategory {
+    /// Get a category from its name in the config, creating a custom category if it isn't one
+    /// of the builtin ones
+    pub fn from_name(name: &str) -> UnitCategory {
+        let name = name.trim().to_lowercase().replace(['_', '-'], " ");
+        match name.as_str() {
+            "length" | "distance" => UnitCategory::Length,
+            "mass" | "weight" => UnitCategory::Mass,
+            "volume" => UnitCategory::Volume,
+            "temperature" => UnitCategory::Temperature,
+            "duration" | "time" => UnitCategory::Duration,
+            "area" => UnitCategory::Area,
+            "speed" => UnitCategory::Speed,
+            "digital storage" | "data" => UnitCategory::DigitalStorage,
+            "pressure" => UnitCategory::Pressure,
+            "energy" => UnitCategory::Energy,
+            "power" => UnitCategory::Power,
+            "angle" => UnitCategory::Angle,
+            "fuel economy" => UnitCategory::FuelEconomy,
+            "fuel consumption" => UnitCategory::FuelConsumption,
+            "currency" | "money" => UnitCategory::Currency,
+            _ => UnitCategory::Custom(intern(&name)),
+        }
+    }
+
+    /// The dimension that every unit in this category has
+    pub fn dimension(&self) -> Dimension {
+        let none = Dimension::default();
+        match self {
+            UnitCategory::Length => Dimension { length: 1, ..none },
+            UnitCategory::Mass => Dimension { mass: 1, ..none },
+            UnitCategory::Volume => Dimension { length: 3, ..none },
+            UnitCategory::Temperature => Dimension {
+                temperature: 1,
+                ..none
+            },
+            UnitCategory::Duration => Dimension { time: 1, ..none },
+            UnitCategory::Area => Dimension { length: 2, ..none },
+            UnitCategory::Speed => Dimension {
+                length: 1,
+                time: -1,
+                ..none
+            },
+            UnitCategory::DigitalStorage => Dimension {
+                information: 1,
+                ..none
+            },
+            UnitCategory::Pressure => Dimension {
+                mass: 1,
+                length: -1,
+                time: -2,
+                ..none
+            },
+            UnitCategory::Energy => Dimension {
+                mass: 1,
+                length: 2,
+                time: -2,
+                ..none
+            },
+            UnitCategory::Power => Dimension {
+                mass: 1,
+                length: 2,
+                time: -3,
+                ..none
+            },
+            UnitCategory::Angle => Dimension { angle: 1, ..none },
+            UnitCategory::FuelEconomy => Dimension { length: -2, ..none },
+            UnitCategory::FuelConsumption => Dimension { length: 2, ..none },
+            UnitCategory::Currency => Dimension {
+                currency: 1,
+                ..none
+            },
+            // Every custom category is its own base dimension
+            UnitCategory::Custom(name) => Dimension {
+                custom: vec![(name, 1)],
+                ..none
+            },
+        }
+    }
+
+    /// How many SI base units (m, kg, s, K) the base unit of this category is worth, so that
+    /// units from different categories can be combined (e.g. a litre is 0.001 m^3)
+    fn si_scale(&self) -> f64 {
+        match self {
+            UnitCategory::Mass | UnitCategory::Volume => 0.001,
+            // km/L and L/km
+            UnitCategory::FuelEconomy => 1e6,
+            UnitCategory::FuelConsumption => 1e-6,
+            _ => 1.0,
+        }
+    }
+
+    /// The category that this one is converted to by taking the reciprocal, like going from
+    /// miles per gallon to litres per 100 km
+    pub fn reciprocal(&self) -> Option<UnitCategory> {
+        match self {
+            UnitCategory::FuelEconomy => Some(UnitCategory::FuelConsumption),
+            UnitCategory::FuelConsumption => Some(UnitCategory::FuelEconomy),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// The exponents of the base dimensions that a quantity is made of
+///
+/// For example, a speed is `length: 1, time: -1`, and a plain number has every exponent at 0
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Dimension {
+    pub length: i32,
+    pub mass: i32,
+    pub time: i32,
+    pub temperature: i32,
+    pub information: i32,
+    pub angle: i32,
+    pub currency: i32,
+    /// The exponents of custom categories, sorted by name
+    pub custom: Vec<(&'static str, i32)>,
+}
+
+impl Dimension {
+    /// Raise the dimension to a power, (L^2)^-1 is L^-2
+    fn powi(&self, power: i32) -> Self {
+        Self {
+            length: self.length * power,
+            mass: self.mass * power,
+            time: self.time * power,
+            temperature: self.temperature * power,
+            information: self.information * power,
+            angle: self.angle * power,
+            currency: self.currency * power,
+            custom: self
+                .custom
+                .iter()
+                .map(|(name, exp)| (*name, exp * power))
+                .filter(|(_, exp)| *exp != 0)
+                .collect(),
+        }
+    }
+
+    /// Multiply two dimensions together, L * L^-1 is dimensionless
+    fn mul(&self, other: &Self) -> Self {
+        let mut custom = self.custom.clone();
+        for &(name, exp) in &other.custom {
+            match custom.iter_mut().find(|(existing, _)| *existing == name) {
+                Some(existing) => existing.1 += exp,
+                None => custom.push((name, exp)),
+            }
+        }
+        custom.retain(|(_, exp)| *exp != 0);
+        custom.sort_by_key(|(name, _)| *name);
+
+        Self {
+            length: self.length + other.length,
+            mass: self.mass + other.mass,
+            time: self.time + other.time,
+            temperature: self.temperature + other.temperature,
+            information: self.information + other.information,
+            angle: self.angle + other.angle,
+            currency: self.currency + other.currency,
+            custom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct UnitDef {
     pub name: &'static str,
     pub aliases: &'static [&'static str],
@@ -17,19 +265,258 @@ pub struct UnitDef {
     pub offset: f64,
 }
 
+/// A product of units raised to powers, e.g. `km/h` is `[(km, 1), (h, -1)]`
+pub type Units = Vec<(&'static UnitDef, i32)>;
+
+/// A number with units attached to it, which is what unit-aware expressions evaluate to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub units: Units,
+}
+
+impl Quantity {
+    /// A plain number without any units
+    pub fn scalar(value: f64) -> Self {
+        Self {
+            value,
+            units: vec![],
+        }
+    }
+
+    /// The dimension of the quantity, based on its units
+    pub fn dimension(&self) -> Dimension {
+        units_dimension(&self.units)
+    }
+
+    /// Returns the quantity as a plain number if all of its units cancel out
+    pub fn to_scalar(&self) -> Option<f64> {
+        if self.dimension() == Dimension::default() {
+            Some(self.value * si_factor(&self.units))
+        } else {
+            None
+        }
+    }
+
+    /// The value of the quantity in the base unit of a category, used to define units in terms
+    /// of other units
+    pub fn base_value(&self, category: UnitCategory) -> Option<f64> {
+        if self.dimension() == category.dimension() {
+            Some(self.value * si_factor(&self.units) / category.si_scale())
+        } else {
+            None
+        }
+    }
+
+    /// Add two quantities together, keeping the units of the left hand side
+    pub fn add(self, rhs: Quantity) -> Result<Quantity, String> {
+        let rhs = rhs.convert_to(&self.units).map_err(|_| {
+            format!(
+                "Cannot add {} and {}",
+                units_name(&self.units),
+                units_name(&rhs.units)
+            )
+        })?;
+        Ok(Quantity {
+            value: self.value + rhs.value,
+            units: self.units,
+        })
+    }
+
+    /// Subtract a quantity from another, keeping the units of the left hand side
+    pub fn sub(self, rhs: Quantity) -> Result<Quantity, String> {
+        let rhs = rhs.convert_to(&self.units).map_err(|_| {
+            format!(
+                "Cannot subtract {} from {}",
+                units_name(&rhs.units),
+                units_name(&self.units)
+            )
+        })?;
+        Ok(Quantity {
+            value: self.value - rhs.value,
+            units: self.units,
+        })
+    }
+
+    /// Multiply two quantities together, combining their units
+    pub fn mul(self, rhs: Quantity) -> Quantity {
+        let (units, factor) = merge_units(self.units, &rhs.units, 1);
+        Quantity {
+            value: self.value * rhs.value * factor,
+            units,
+        }
+    }
+
+    /// Divide a quantity by another, combining their units
+    pub fn div(self, rhs: Quantity) -> Quantity {
+        let (units, factor) = merge_units(self.units, &rhs.units, -1);
+        Quantity {
+            value: self.value / rhs.value * factor,
+            units,
+        }
+    }
+
+    /// Raise the quantity (and its units) to a whole power
+    pub fn powi(self, power: i32) -> Quantity {
+        Quantity {
+            value: self.value.powi(power),
+            units: self
+                .units
+                .into_iter()
+                .map(|(unit, exp)| (unit, exp * power))
+                .collect(),
+        }
+    }
+
+    /// Convert the quantity into the provided units, failing if their dimensions don't match
+    pub fn convert_to(&self, target: &[(&'static UnitDef, i32)]) -> Result<Quantity, String> {
+        let (source, target) = read_as_currencies(self, target, &currency_units());
+        let from = source.dimension();
+        let to = units_dimension(&target);
+
+        let value = match (source.units.as_slice(), target.as_slice()) {
+            // Single units go through their offsets, so that temperatures convert properly
+            ([(from, 1)], [(to, 1)]) if from.category == to.category => {
+                from_base(to_base(source.value, from), to)
+            }
+            _ if from == to => source.value * si_factor(&source.units) / si_factor(&target),
+            _ if is_reciprocal(&from, &to) => {
+                1.0 / (source.value * si_factor(&source.units) * si_factor(&target))
+            }
+            _ => {
+                return Err(format!(
+                    "Cannot convert {} to {}",
+                    units_name(&source.units),
+                    units_name(&target)
+                ));
+            }
+        };
+
+        Ok(Quantity {
+            value,
+            units: target,
+        })
+    }
+
+    /// The units of the quantity written out, e.g. `km/h` or `kg*m/s^2`
+    pub fn unit_label(&self) -> String {
+        let write = |units: Vec<(&UnitDef, i32)>| {
+            units
+                .into_iter()
+                .map(|(unit, exp)| {
+                    if exp == 1 {
+                        unit.name.to_string()
+                    } else {
+                        format!("{}^{}", unit.name, exp)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("*")
+        };
+
+        let numerator = write(
+            self.units
+                .iter()
+                .filter(|(_, exp)| *exp > 0)
+                .copied()
+                .collect(),
+        );
+        let denominator = write(
+            self.units
+                .iter()
+                .filter(|(_, exp)| *exp < 0)
+                .map(|(unit, exp)| (*unit, -exp))
+                .collect(),
+        );
+
+        match (numerator.is_empty(), denominator.is_empty()) {
+            (_, true) => numerator,
+            (true, false) => format!("1/{denominator}"),
+            (false, false) => format!("{numerator}/{denominator}"),
+        }
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.units.as_slice() {
+            [] => write!(f, "{}", format_number(self.value)),
+            // Money is shown to the cent
+            [(unit, 1)] if unit.category == UnitCategory::Currency && self.value.abs() >= 0.01 => {
+                write!(f, "{:.2} {}", self.value, unit.name)
+            }
+            _ => write!(f, "{} {}", format_number(self.value), self.unit_label()),
+        }
+    }
+}
+
+/// What a quantity was converted into
 #[derive(Debug, Clone)]
-pub struct ConversionResult {
-    pub source_value: f64,
-    pub source_unit: &'static UnitDef,
-    pub target_value: f64,
-    pub target_unit: &'static UnitDef,
+pub enum ConversionOutput {
+    /// A single quantity, like `180.34 cm`
+    Single(Quantity),
+    /// A quantity split over several units, like `5 ft 11 in`
+    Mixed(Vec<Quantity>),
+    /// A quantity split over several units like a clock, like `1:30:00`
+    Clock(Vec<Quantity>),
+}
+
+impl fmt::Display for ConversionOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionOutput::Single(quantity) => write!(f, "{quantity}"),
+            ConversionOutput::Mixed(parts) => {
+                let parts: Vec<String> = parts.iter().map(|part| part.to_string()).collect();
+                write!(f, "{}", parts.join(" "))
+            }
+            ConversionOutput::Clock(parts) => {
+                let sign = if parts.iter().any(|part| part.value < 0.0) {
+                    "-"
+                } else {
+                    ""
+                };
+                let parts: Vec<String> = parts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, part)| {
+                        let number = format_number(part.value.abs());
+                        if i > 0 && part.value.abs() < 10.0 {
+                            format!("0{number}")
+                        } else {
+                            number
+                        }
+                    })
+                    .collect();
+                write!(f, "{sign}{}", parts.join(":"))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-struct ParsedQuery {
-    value: f64,
-    source_unit: &'static UnitDef,
-    target_unit: Option<&'static UnitDef>,
+pub struct ConversionResult {
+    pub source: Quantity,
+    pub target: ConversionOutput,
+}
+
+impl ConversionResult {
+    /// The description shown under the result, which says how old the rates of currency
+    /// conversions are
+    pub fn description(&self) -> String {
+        if self.source.dimension().currency == 0 {
+            return self.source.to_string();
+        }
+        let Some(date) = RATES_DATE.read().ok().and_then(|date| date.clone()) else {
+            return self.source.to_string();
+        };
+
+        match currency::age_in_days(&date) {
+            Some(age) if age > STALE_AFTER_DAYS => {
+                format!("{} · ⚠ rates from {date} are {age} days old", self.source)
+            }
+            _ => format!("{} · rates from {date}", self.source),
+        }
+    }
 }
 
 const UNITS: &[UnitDef] = &[
@@ -230,43 +717,689 @@ const UNITS: &[UnitDef] = &[
         scale: 1.0,
         offset: -273.15,
     },
+    // Duration (base: second)
+    UnitDef {
+        name: "ms",
+        aliases: &["ms", "millisecond", "milliseconds"],
+        category: UnitCategory::Duration,
+        scale: 0.001,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "s",
+        aliases: &["s", "sec", "secs", "second", "seconds"],
+        category: UnitCategory::Duration,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "min",
+        aliases: &["min", "mins", "minute", "minutes"],
+        category: UnitCategory::Duration,
+        scale: 60.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "h",
+        aliases: &["h", "hr", "hrs", "hour", "hours"],
+        category: UnitCategory::Duration,
+        scale: 3600.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "day",
+        aliases: &["day", "days", "d"],
+        category: UnitCategory::Duration,
+        scale: 86_400.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "week",
+        aliases: &["week", "weeks", "wk", "wks"],
+        category: UnitCategory::Duration,
+        scale: 604_800.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "month",
+        aliases: &["month", "months", "mo"],
+        category: UnitCategory::Duration,
+        scale: 2_629_746.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "year",
+        aliases: &["year", "years", "yr", "yrs"],
+        category: UnitCategory::Duration,
+        scale: 31_556_952.0,
+        offset: 0.0,
+    },
+    // Area (base: square meter)
+    UnitDef {
+        name: "mm2",
+        aliases: &["mm2", "sqmm"],
+        category: UnitCategory::Area,
+        scale: 0.000_001,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "cm2",
+        aliases: &["cm2", "sqcm"],
+        category: UnitCategory::Area,
+        scale: 0.0001,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "m2",
+        aliases: &["m2", "sqm"],
+        category: UnitCategory::Area,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "ha",
+        aliases: &["ha", "hectare", "hectares"],
+        category: UnitCategory::Area,
+        scale: 10_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "km2",
+        aliases: &["km2", "sqkm"],
+        category: UnitCategory::Area,
+        scale: 1_000_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "in2",
+        aliases: &["in2", "sqin"],
+        category: UnitCategory::Area,
+        scale: 0.000_645_16,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "ft2",
+        aliases: &["ft2", "sqft"],
+        category: UnitCategory::Area,
+        scale: 0.092_903_04,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "yd2",
+        aliases: &["yd2", "sqyd"],
+        category: UnitCategory::Area,
+        scale: 0.836_127_36,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "acre",
+        aliases: &["acre", "acres", "ac"],
+        category: UnitCategory::Area,
+        scale: 4_046.856_422_4,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "mi2",
+        aliases: &["mi2", "sqmi"],
+        category: UnitCategory::Area,
+        scale: 2_589_988.110_336,
+        offset: 0.0,
+    },
+    // Speed (base: meter per second)
+    UnitDef {
+        name: "m/s",
+        aliases: &["mps"],
+        category: UnitCategory::Speed,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "km/h",
+        aliases: &["kph", "kmh", "kmph"],
+        category: UnitCategory::Speed,
+        scale: 1.0 / 3.6,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "ft/s",
+        aliases: &["fps"],
+        category: UnitCategory::Speed,
+        scale: 0.3048,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "mph",
+        aliases: &["mph"],
+        category: UnitCategory::Speed,
+        scale: 0.447_04,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "kn",
+        aliases: &["kn", "kt", "knot", "knots"],
+        category: UnitCategory::Speed,
+        scale: 1852.0 / 3600.0,
+        offset: 0.0,
+    },
+    // Digital storage (base: byte)
+    UnitDef {
+        name: "B",
+        aliases: &["B", "byte", "bytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "kB",
+        aliases: &["kB", "KB", "kilobyte", "kilobytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1e3,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "MB",
+        aliases: &["MB", "megabyte", "megabytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1e6,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "GB",
+        aliases: &["GB", "gigabyte", "gigabytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1e9,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "TB",
+        aliases: &["TB", "terabyte", "terabytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1e12,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "PB",
+        aliases: &["PB", "petabyte", "petabytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1e15,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "KiB",
+        aliases: &["KiB", "kibibyte", "kibibytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1024.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "MiB",
+        aliases: &["MiB", "mebibyte", "mebibytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1_048_576.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "GiB",
+        aliases: &["GiB", "gibibyte", "gibibytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1_073_741_824.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "TiB",
+        aliases: &["TiB", "tebibyte", "tebibytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1_099_511_627_776.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "PiB",
+        aliases: &["PiB", "pebibyte", "pebibytes"],
+        category: UnitCategory::DigitalStorage,
+        scale: 1_125_899_906_842_624.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "bit",
+        aliases: &["b", "bit", "bits"],
+        category: UnitCategory::DigitalStorage,
+        scale: 0.125,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "kbit",
+        aliases: &["kbit", "Kb", "kilobit", "kilobits"],
+        category: UnitCategory::DigitalStorage,
+        scale: 125.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "Mbit",
+        aliases: &["Mbit", "Mb", "megabit", "megabits"],
+        category: UnitCategory::DigitalStorage,
+        scale: 125_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "Gbit",
+        aliases: &["Gbit", "Gb", "gigabit", "gigabits"],
+        category: UnitCategory::DigitalStorage,
+        scale: 125_000_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "Tbit",
+        aliases: &["Tbit", "Tb", "terabit", "terabits"],
+        category: UnitCategory::DigitalStorage,
+        scale: 125_000_000_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "Kibit",
+        aliases: &["Kibit", "kibibit", "kibibits"],
+        category: UnitCategory::DigitalStorage,
+        scale: 128.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "Mibit",
+        aliases: &["Mibit", "mebibit", "mebibits"],
+        category: UnitCategory::DigitalStorage,
+        scale: 131_072.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "Gibit",
+        aliases: &["Gibit", "gibibit", "gibibits"],
+        category: UnitCategory::DigitalStorage,
+        scale: 134_217_728.0,
+        offset: 0.0,
+    },
+    // Pressure (base: pascal)
+    UnitDef {
+        name: "Pa",
+        aliases: &["Pa", "pascal", "pascals"],
+        category: UnitCategory::Pressure,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "hPa",
+        aliases: &["hPa", "hectopascal", "hectopascals"],
+        category: UnitCategory::Pressure,
+        scale: 100.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "kPa",
+        aliases: &["kPa", "kilopascal", "kilopascals"],
+        category: UnitCategory::Pressure,
+        scale: 1000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "MPa",
+        aliases: &["MPa", "megapascal", "megapascals"],
+        category: UnitCategory::Pressure,
+        scale: 1_000_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "mbar",
+        aliases: &["mbar", "millibar", "millibars"],
+        category: UnitCategory::Pressure,
+        scale: 100.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "bar",
+        aliases: &["bar", "bars"],
+        category: UnitCategory::Pressure,
+        scale: 100_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "psi",
+        aliases: &["psi"],
+        category: UnitCategory::Pressure,
+        scale: 6_894.757_293_168,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "atm",
+        aliases: &["atm", "atmosphere", "atmospheres"],
+        category: UnitCategory::Pressure,
+        scale: 101_325.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "mmHg",
+        aliases: &["mmHg"],
+        category: UnitCategory::Pressure,
+        scale: 133.322_387_415,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "inHg",
+        aliases: &["inHg"],
+        category: UnitCategory::Pressure,
+        scale: 3_386.388_666_6,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "torr",
+        aliases: &["torr"],
+        category: UnitCategory::Pressure,
+        scale: 101_325.0 / 760.0,
+        offset: 0.0,
+    },
+    // Energy (base: joule)
+    UnitDef {
+        name: "J",
+        aliases: &["J", "joule", "joules"],
+        category: UnitCategory::Energy,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "kJ",
+        aliases: &["kJ", "kilojoule", "kilojoules"],
+        category: UnitCategory::Energy,
+        scale: 1000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "MJ",
+        aliases: &["MJ", "megajoule", "megajoules"],
+        category: UnitCategory::Energy,
+        scale: 1_000_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "cal",
+        aliases: &["cal", "calorie", "calories"],
+        category: UnitCategory::Energy,
+        scale: 4.184,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "kcal",
+        aliases: &["kcal", "Cal", "kilocalorie", "kilocalories"],
+        category: UnitCategory::Energy,
+        scale: 4_184.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "Wh",
+        aliases: &["Wh"],
+        category: UnitCategory::Energy,
+        scale: 3_600.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "kWh",
+        aliases: &["kWh"],
+        category: UnitCategory::Energy,
+        scale: 3_600_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "BTU",
+        aliases: &["BTU", "btu"],
+        category: UnitCategory::Energy,
+        scale: 1_055.055_852_62,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "eV",
+        aliases: &["eV", "electronvolt", "electronvolts"],
+        category: UnitCategory::Energy,
+        scale: 1.602_176_634e-19,
+        offset: 0.0,
+    },
+    // Power (base: watt)
+    UnitDef {
+        name: "mW",
+        aliases: &["mW", "milliwatt", "milliwatts"],
+        category: UnitCategory::Power,
+        scale: 0.001,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "W",
+        aliases: &["W", "watt", "watts"],
+        category: UnitCategory::Power,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "kW",
+        aliases: &["kW", "kilowatt", "kilowatts"],
+        category: UnitCategory::Power,
+        scale: 1000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "MW",
+        aliases: &["MW", "megawatt", "megawatts"],
+        category: UnitCategory::Power,
+        scale: 1_000_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "hp",
+        aliases: &["hp", "horsepower"],
+        category: UnitCategory::Power,
+        scale: 745.699_871_582_270_2,
+        offset: 0.0,
+    },
+    // Angle (base: radian)
+    UnitDef {
+        name: "rad",
+        aliases: &["rad", "radian", "radians"],
+        category: UnitCategory::Angle,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "deg",
+        aliases: &["deg", "degree", "degrees"],
+        category: UnitCategory::Angle,
+        scale: PI / 180.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "grad",
+        aliases: &["grad", "gon", "gradian", "gradians"],
+        category: UnitCategory::Angle,
+        scale: PI / 200.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "arcmin",
+        aliases: &["arcmin", "arcminute", "arcminutes"],
+        category: UnitCategory::Angle,
+        scale: PI / 10_800.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "arcsec",
+        aliases: &["arcsec", "arcsecond", "arcseconds"],
+        category: UnitCategory::Angle,
+        scale: PI / 648_000.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "turn",
+        aliases: &["turn", "turns", "rev", "revolution", "revolutions"],
+        category: UnitCategory::Angle,
+        scale: TAU,
+        offset: 0.0,
+    },
+    // Fuel economy (base: kilometer per liter)
+    UnitDef {
+        name: "km/L",
+        aliases: &["kmpl", "kml"],
+        category: UnitCategory::FuelEconomy,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "mpg",
+        aliases: &["mpg", "mpgus"],
+        category: UnitCategory::FuelEconomy,
+        scale: 0.425_143_707_430_272_7,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "mpg (UK)",
+        aliases: &["mpguk", "mpgimp"],
+        category: UnitCategory::FuelEconomy,
+        scale: 0.354_006_189_934_647_4,
+        offset: 0.0,
+    },
+    // Fuel consumption (base: liter per kilometer)
+    UnitDef {
+        name: "L/100km",
+        aliases: &["l100km", "lp100km"],
+        category: UnitCategory::FuelConsumption,
+        scale: 0.01,
+        offset: 0.0,
+    },
+    UnitDef {
+        name: "gal/100mi",
+        aliases: &["gal100mi"],
+        category: UnitCategory::FuelConsumption,
+        scale: 0.023_521_458_333_333_33,
+        offset: 0.0,
+    },
 ];
 
-pub fn convert_query(query: &str) -> Option<Vec<ConversionResult>> {
-    let parsed = parse_query(query)?;
-    let base_value = to_base(parsed.value, parsed.source_unit);
+pub fn convert_query(query: &str) -> Option<Result<Vec<ConversionResult>, String>> {
+    // Don't treat words that happen to be unit names (like `in` or `m`) as conversions
+    if !query.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
 
-    let mut results = Vec::new();
-    let targets: Vec<&UnitDef> = match parsed.target_unit {
-        Some(target) => vec![target],
-        None => UNITS
-            .iter()
-            .filter(|unit| unit.category == parsed.source_unit.category)
-            .collect(),
+    let expr = Expr::from_str(query).ok()?;
+    if !expr.has_units() {
+        return None;
+    }
+
+    // `<expr> to ft+in` or `<expr> to h:min:s`
+    if let Expr::ConvertMixed { expr, units, clock } = expr {
+        let source = match expr.eval_quantity() {
+            Ok(source) => source,
+            Err(e) => return Some(Err(e)),
+        };
+        return Some(split_quantity(&source, &units).map(|parts| {
+            vec![ConversionResult {
+                source,
+                target: if clock {
+                    ConversionOutput::Clock(parts)
+                } else {
+                    ConversionOutput::Mixed(parts)
+                },
+            }]
+        }));
+    }
+
+    let (expr, target) = match expr {
+        Expr::Convert { expr, target } => (*expr, Some(target)),
+        expr => (expr, None),
     };
 
-    for target_unit in targets {
-        if target_unit.name == parsed.source_unit.name {
-            continue;
-        }
-        let target_value = from_base(base_value, target_unit);
+    let source = match expr.eval_quantity() {
+        Ok(source) => source,
+        Err(e) => return Some(Err(e)),
+    };
+
+    if let Some(target) = target {
+        let (source, target) = read_as_currencies(&source, &target, &currency_units());
+        return Some(source.convert_to(&target).map(|target| {
+            vec![ConversionResult {
+                source: source.clone(),
+                target: ConversionOutput::Single(target),
+            }]
+        }));
+    }
+
+    let category = category_of(&source);
+    let reciprocal = category.and_then(|category| category.reciprocal());
+
+    let mut results: Vec<ConversionResult> = all_units()
+        .into_iter()
+        .filter(|unit| Some(unit.category) == category || Some(unit.category) == reciprocal)
+        .filter(|unit| source.units.as_slice() != [(*unit, 1)] && unit.name != source.unit_label())
+        .filter_map(|unit| {
+            Some(ConversionResult {
+                source: source.clone(),
+                target: ConversionOutput::Single(source.convert_to(&[(unit, 1)]).ok()?),
+            })
+        })
+        .collect();
+
+    // Derived units that aren't in the table (like km/h) are shown as they are
+    if results.is_empty() {
         results.push(ConversionResult {
-            source_value: parsed.value,
-            source_unit: parsed.source_unit,
-            target_value,
-            target_unit,
+            source: source.clone(),
+            target: ConversionOutput::Single(source),
         });
     }
 
-    if results.is_empty() {
-        None
-    } else {
-        Some(results)
+    Some(Ok(results))
+}
+
+/// Split a quantity over several units, largest first, like `71 in` into `5 ft 11 in`
+///
+/// Every unit but the last gets a whole number, and the last unit gets whatever is left over
+pub fn split_quantity(
+    source: &Quantity,
+    units: &[&'static UnitDef],
+) -> Result<Vec<Quantity>, String> {
+    let Some((last, larger)) = units.split_last() else {
+        return Err("No units to convert to".to_string());
+    };
+
+    // Rounding keeps float noise from turning `72 in` into `5 ft 12 in`
+    let total = source.convert_to(&[(*last, 1)])?.value;
+    let sign = total.signum();
+    let mut remaining = (total.abs() * 1e6).round() / 1e6;
+
+    let mut parts = Vec::new();
+    for unit in larger {
+        let size = Quantity {
+            value: 1.0,
+            units: vec![(*unit, 1)],
+        }
+        .convert_to(&[(*last, 1)])?
+        .value;
+        let count = (remaining / size + 1e-9).floor();
+        remaining = (remaining - count * size).max(0.0);
+        parts.push((*unit, count));
     }
+    parts.push((*last, (remaining * 1e6).round() / 1e6));
+
+    Ok(parts
+        .into_iter()
+        .map(|(unit, value)| Quantity {
+            value: value * sign,
+            units: vec![(unit, 1)],
+        })
+        .collect())
 }
 
 pub fn format_number(value: f64) -> String {
-    let value = if value.abs() < 1e-9 { 0.0 } else { value };
+    // Avoids printing -0
+    let value = if value == 0.0 { 0.0 } else { value };
+    // Very small or large values (like electronvolts in joules) would be unreadable otherwise
+    if value != 0.0 && !(1e-4..1e15).contains(&value.abs()) {
+        let s = format!("{value:.6e}");
+        let (mantissa, exponent) = s.split_once('e').unwrap_or((&s, "0"));
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        return format!("{mantissa}e{exponent}");
+    }
+
     let mut s = format!("{value:.6}");
     if let Some(dot_pos) = s.find('.') {
         while s.ends_with('0') {
@@ -279,98 +1412,195 @@ pub fn format_number(value: f64) -> String {
     s
 }
 
-fn parse_query(query: &str) -> Option<ParsedQuery> {
-    let (value_str, rest) = parse_number_prefix(query)?;
-    let value: f64 = value_str.parse().ok()?;
-    let rest = rest.trim_start();
-    if rest.is_empty() {
-        return None;
-    }
-
-    let rest_lc = rest.to_lowercase();
-    let tokens: Vec<&str> = rest_lc.split_whitespace().collect();
-    if tokens.is_empty() {
+/// Find a unit by its name or one of its aliases
+///
+/// Exact matches are preferred so that case sensitive units (like `Mb` and `MB`) can be told
+/// apart, falling back to a case insensitive match
+pub fn find_unit(token: &str) -> Option<&'static UnitDef> {
+    let token = token.trim();
+    if token.is_empty() {
         return None;
     }
 
-    let source_unit = find_unit(tokens[0])?;
-    match tokens.len() {
-        1 => Some(ParsedQuery {
-            value,
-            source_unit,
-            target_unit: None,
-        }),
-        2 => {
-            let target_unit = find_unit(tokens[1])?;
-            if target_unit.category != source_unit.category {
-                return None;
-            }
-            Some(ParsedQuery {
-                value,
-                source_unit,
-                target_unit: Some(target_unit),
-            })
-        }
-        3 if tokens[1] == "to" || tokens[1] == "in" => {
-            let target_unit = find_unit(tokens[2])?;
-            if target_unit.category != source_unit.category {
-                return None;
-            }
-            Some(ParsedQuery {
-                value,
-                source_unit,
-                target_unit: Some(target_unit),
+    let units = all_units();
+    units
+        .iter()
+        .find(|unit| unit.name == token || unit.aliases.contains(&token))
+        .or_else(|| {
+            units.iter().find(|unit| {
+                unit.name.eq_ignore_ascii_case(token)
+                    || unit
+                        .aliases
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(token))
             })
-        }
-        _ => None,
+        })
+        .copied()
+}
+
+/// A conversion between single units, with the unit on one side read as a currency when the other
+/// side is one and a currency goes by the same name
+///
+/// `pound` is both a mass and a currency, so `10 pounds to eur` is about GBP
+fn read_as_currencies(
+    source: &Quantity,
+    target: &[(&'static UnitDef, i32)],
+    currencies: &[&'static UnitDef],
+) -> (Quantity, Units) {
+    let (source, target) = (source.clone(), target.to_vec());
+    let ([(from, 1)], [(to, 1)]) = (source.units.as_slice(), target.as_slice()) else {
+        return (source, target);
+    };
+    if to.category == UnitCategory::Currency
+        && from.category != UnitCategory::Currency
+        && let Some(from) = currency_named_like(from, currencies)
+    {
+        return (
+            Quantity {
+                value: source.value,
+                units: vec![(from, 1)],
+            },
+            target,
+        );
+    }
+    if from.category == UnitCategory::Currency
+        && to.category != UnitCategory::Currency
+        && let Some(to) = currency_named_like(to, currencies)
+    {
+        return (source, vec![(to, 1)]);
     }
+    (source, target)
 }
 
-fn parse_number_prefix(s: &str) -> Option<(&str, &str)> {
-    let s = s.trim_start();
-    if s.is_empty() {
-        return None;
+/// The currency that goes by one of the names of another unit, like GBP for the `pound`
+fn currency_named_like(
+    unit: &UnitDef,
+    currencies: &[&'static UnitDef],
+) -> Option<&'static UnitDef> {
+    currencies
+        .iter()
+        .find(|currency| {
+            currency
+                .aliases
+                .iter()
+                .any(|alias| unit.aliases.contains(alias))
+        })
+        .copied()
+}
+
+/// The category that a quantity is listed against
+///
+/// A single unit is in its own category. Derived units (like `mi/gal`) use the first category
+/// with the same dimension, except that a volume over a length (like `l/100km`) is a fuel
+/// consumption, which has the same dimension as an area.
+pub fn category_of(quantity: &Quantity) -> Option<UnitCategory> {
+    let units = quantity.units.as_slice();
+    if let [(unit, 1)] = units {
+        return Some(unit.category);
     }
 
-    let mut chars = s.char_indices().peekable();
-    if let Some((_, c)) = chars.peek()
-        && (*c == '+' || *c == '-')
+    let is_volume =
+        |(unit, power): &(&UnitDef, i32)| unit.category == UnitCategory::Volume && *power == 1;
+    let is_per_length =
+        |(unit, power): &(&UnitDef, i32)| unit.category == UnitCategory::Length && *power == -1;
+    if units.iter().any(is_volume)
+        && units.iter().any(is_per_length)
+        && units
+            .iter()
+            .all(|unit| is_volume(unit) || is_per_length(unit))
     {
-        chars.next();
-    }
-
-    let mut end = 0;
-    let mut has_digit = false;
-    while let Some((idx, c)) = chars.peek().cloned() {
-        if c.is_ascii_digit() {
-            has_digit = true;
-            end = idx + c.len_utf8();
-            chars.next();
-        } else if c == '.' {
-            end = idx + c.len_utf8();
-            chars.next();
-        } else {
-            break;
-        }
+        return Some(UnitCategory::FuelConsumption);
     }
 
-    if !has_digit || end == 0 {
-        return None;
+    let dimension = quantity.dimension();
+    all_units()
+        .into_iter()
+        .map(|unit| unit.category)
+        .find(|category| category.dimension() == dimension)
+}
+
+/// Replace the user's own units with the ones in their config
+///
+/// Units are added in order, so a unit can be defined in terms of one that comes before it
+pub fn load_custom_units(units: &[CustomUnit]) {
+    if let Ok(mut custom) = CUSTOM_UNITS.write() {
+        custom.clear();
     }
 
-    let (num, rest) = s.split_at(end);
-    Some((num, rest))
+    for unit in units {
+        match unit.to_unit_def() {
+            Ok(def) => {
+                if let Ok(mut custom) = CUSTOM_UNITS.write() {
+                    custom.push(intern_unit(def));
+                }
+            }
+            Err(e) => log_error(&format!("Unable to load unit {}: {e}", unit.name)),
+        }
+    }
 }
 
-fn find_unit(token: &str) -> Option<&'static UnitDef> {
-    let token = token.trim();
-    if token.is_empty() {
-        return None;
+/// Load the exchange rates from the rates file into currency units, each of which is scaled
+/// against the base currency of the file
+pub fn load_currency_rates() {
+    let rates = match currency::load_rates() {
+        Some(Ok(rates)) => Some(rates),
+        Some(Err(e)) => {
+            log_error(&format!("Unable to load the currency rates: {e}"));
+            None
+        }
+        None => None,
+    };
+
+    let mut units: Vec<&'static UnitDef> = Vec::new();
+    if let Some(rates) = &rates {
+        let base = std::iter::once((rates.base.clone(), 1.0));
+        for (code, rate) in base.chain(rates.rates.iter().cloned()) {
+            if rate <= 0.0 || units.iter().any(|unit| unit.name == code) {
+                continue;
+            }
+            let aliases = CURRENCY_ALIASES
+                .iter()
+                .find(|(known, _)| *known == code)
+                .map(|(_, aliases)| *aliases)
+                .unwrap_or(&[]);
+            units.push(intern_unit(UnitDef {
+                name: intern(&code),
+                aliases,
+                category: UnitCategory::Currency,
+                scale: 1.0 / rate,
+                offset: 0.0,
+            }));
+        }
     }
 
-    UNITS
-        .iter()
-        .find(|unit| unit.name == token || unit.aliases.contains(&token))
+    if let Ok(mut currencies) = CURRENCY_UNITS.write() {
+        *currencies = units;
+    }
+    if let Ok(mut date) = RATES_DATE.write() {
+        *date = rates.map(|rates| rates.date);
+    }
+}
+
+/// All the units that can be used, with the user's own units taking priority over the builtin
+/// ones
+fn all_units() -> Vec<&'static UnitDef> {
+    let custom = CUSTOM_UNITS
+        .read()
+        .map(|units| units.clone())
+        .unwrap_or_default();
+    custom
+        .into_iter()
+        .chain(UNITS.iter())
+        .chain(currency_units())
+        .collect()
+}
+
+/// The currencies from the exchange rates, which are empty until the rates are loaded
+fn currency_units() -> Vec<&'static UnitDef> {
+    CURRENCY_UNITS
+        .read()
+        .map(|units| units.clone())
+        .unwrap_or_default()
 }
 
 fn to_base(value: f64, unit: &UnitDef) -> f64 {
@@ -380,3 +1610,233 @@ fn to_base(value: f64, unit: &UnitDef) -> f64 {
 fn from_base(value: f64, unit: &UnitDef) -> f64 {
     value / unit.scale - unit.offset
 }
+
+/// The dimension of a product of units
+fn units_dimension(units: &[(&UnitDef, i32)]) -> Dimension {
+    units.iter().fold(Dimension::default(), |dim, (unit, exp)| {
+        dim.mul(&unit.category.dimension().powi(*exp))
+    })
+}
+
+/// Whether two dimensions belong to categories that are the reciprocals of each other
+fn is_reciprocal(from: &Dimension, to: &Dimension) -> bool {
+    *from == to.powi(-1)
+        && all_units().into_iter().any(|unit| {
+            unit.category.dimension() == *from
+                && unit
+                    .category
+                    .reciprocal()
+                    .is_some_and(|reciprocal| reciprocal.dimension() == *to)
+        })
+}
+
+/// How many SI base units a product of units is worth (ignoring offsets)
+fn si_factor(units: &[(&UnitDef, i32)]) -> f64 {
+    units
+        .iter()
+        .map(|(unit, exp)| (unit.scale * unit.category.si_scale()).powi(*exp))
+        .product()
+}
+
+/// A human readable name for a product of units, for use in error messages
+fn units_name(units: &[(&'static UnitDef, i32)]) -> String {
+    if units.is_empty() {
+        "a plain number".to_string()
+    } else {
+        Quantity {
+            value: 1.0,
+            units: units.to_vec(),
+        }
+        .unit_label()
+    }
+}
+
+/// Multiplies (or divides, with a sign of -1) two products of units together
+///
+/// Units of the same category are folded into the one that came first, so `km * m` becomes
+/// `km^2`, and the returned factor is what the value has to be scaled by to account for it.
+fn merge_units(mut units: Units, other: &[(&'static UnitDef, i32)], sign: i32) -> (Units, f64) {
+    let mut factor = 1.0;
+    for &(unit, exp) in other {
+        let exp = exp * sign;
+        if let Some(existing) = units.iter_mut().find(|(u, _)| u.category == unit.category) {
+            factor *= (unit.scale / existing.0.scale).powi(exp);
+            existing.1 += exp;
+        } else {
+            units.push((unit, exp));
+        }
+    }
+    units.retain(|(_, exp)| *exp != 0);
+    (units, factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A value in one unit, and what it is in another, for each category that converts between
+    /// its own units
+    const CONVERSIONS: &[(f64, &str, &str, f64)] = &[
+        // Temperature goes through the offsets
+        (100.0, "c", "f", 212.0),
+        (0.0, "c", "k", 273.15),
+        (-40.0, "f", "c", -40.0),
+        (32.0, "f", "k", 273.15),
+        // Area
+        (1.0, "ha", "m2", 10_000.0),
+        (1.0, "acre", "ft2", 43_560.0),
+        (1.0, "mi2", "km2", 2.589_988_110_336),
+        // Speed
+        (100.0, "km/h", "mph", 62.137_119_223_733_4),
+        (1.0, "kn", "m/s", 0.514_444_444_444_444_4),
+        // Digital storage, where kB and KiB are not the same
+        (1.0, "kB", "KiB", 0.976_562_5),
+        (1.0, "KiB", "B", 1024.0),
+        (1.0, "GiB", "MB", 1_073.741_824),
+        (8.0, "bit", "B", 1.0),
+        (1.0, "Mbit", "kB", 125.0),
+        // Pressure
+        (1.0, "atm", "kPa", 101.325),
+        (1.0, "bar", "psi", 14.503_773_773_020_9),
+        (1.0, "mmHg", "Pa", 133.322_387_415),
+        // Energy
+        (1.0, "kWh", "MJ", 3.6),
+        (1.0, "kcal", "kJ", 4.184),
+        (1.0, "BTU", "J", 1_055.055_852_62),
+        // Power
+        (1.0, "hp", "W", 745.699_871_582_270_2),
+        (1.0, "MW", "kW", 1000.0),
+        // Angle
+        (180.0, "deg", "rad", PI),
+        (1.0, "turn", "deg", 360.0),
+        (1.0, "deg", "arcmin", 60.0),
+        // Fuel economy and consumption, which are the reciprocals of each other
+        (30.0, "mpg", "L/100km", 7.840_486_111_111_11),
+        (5.0, "L/100km", "km/L", 20.0),
+        (40.0, "mpg (UK)", "L/100km", 7.062_023_408_295_555),
+        (10.0, "km/L", "mpg", 23.521_458_333_333_33),
+    ];
+
+    fn convert(value: f64, from: &str, to: &str) -> f64 {
+        let from = find_unit(from).unwrap_or_else(|| panic!("{from} is not a unit"));
+        let to = find_unit(to).unwrap_or_else(|| panic!("{to} is not a unit"));
+        Quantity {
+            value,
+            units: vec![(from, 1)],
+        }
+        .convert_to(&[(to, 1)])
+        .unwrap_or_else(|e| panic!("{e}"))
+        .value
+    }
+
+    fn assert_close(actual: f64, expected: f64, what: &str) {
+        let tolerance = 1e-9 * expected.abs().max(1.0);
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{what}: got {actual}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        for &(value, from, to, expected) in CONVERSIONS {
+            assert_close(
+                convert(value, from, to),
+                expected,
+                &format!("{value} {from} to {to}"),
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        for &(value, from, to, _) in CONVERSIONS {
+            let there = convert(value, from, to);
+            assert_close(
+                convert(there, to, from),
+                value,
+                &format!("{value} {from} to {to} and back"),
+            );
+        }
+    }
+
+    #[test]
+    fn fuel_consumption_is_not_an_area() {
+        let Some(Ok(results)) = convert_query("5 l/100km") else {
+            panic!("5 l/100km is a quantity");
+        };
+        let targets = results
+            .iter()
+            .filter_map(|result| match &result.target {
+                ConversionOutput::Single(target) => Some(target),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(!targets.is_empty());
+        for target in &targets {
+            assert!(
+                matches!(
+                    category_of(target),
+                    Some(UnitCategory::FuelConsumption | UnitCategory::FuelEconomy)
+                ),
+                "5 l/100km was listed as {}",
+                target.unit_label()
+            );
+        }
+        let km_per_l = targets
+            .iter()
+            .find(|target| target.unit_label() == "km/L")
+            .expect("5 l/100km is listed in km/L");
+        assert_close(km_per_l.value, 20.0, "5 l/100km to km/L");
+    }
+
+    #[test]
+    fn reloading_reuses_units() {
+        let unit = UnitDef {
+            name: intern(&String::from("smoot")),
+            aliases: intern_aliases(&["smoots"]),
+            category: UnitCategory::Custom(intern("Smoots")),
+            scale: 1.7018,
+            offset: 0.0,
+        };
+        let first = intern_unit(unit);
+        let second = intern_unit(unit);
+        assert!(std::ptr::eq(first, second));
+        assert!(std::ptr::eq(first.name, intern("smoot")));
+        assert!(std::ptr::eq(first.aliases, intern_aliases(&["smoots"])));
+    }
+
+    #[test]
+    fn pounds_are_currency_next_to_a_currency() {
+        let currency = |name, aliases, rate: f64| {
+            intern_unit(UnitDef {
+                name,
+                aliases,
+                category: UnitCategory::Currency,
+                scale: 1.0 / rate,
+                offset: 0.0,
+            })
+        };
+        let eur = currency("EUR", &["€", "euro", "euros"], 1.0);
+        let gbp = currency("GBP", &["£", "pound", "pounds"], 0.5);
+        let pound = find_unit("pound").expect("pound is a unit");
+        let kg = find_unit("kg").expect("kg is a unit");
+        // The table is passed in, as the other tests share the loaded currencies
+        let convert = |value, from, to| {
+            let source = Quantity {
+                value,
+                units: vec![(from, 1)],
+            };
+            let (source, target) = read_as_currencies(&source, &[(to, 1)], &[eur, gbp]);
+            source.convert_to(&target).unwrap_or_else(|e| panic!("{e}"))
+        };
+
+        let euros = convert(10.0, pound, eur);
+        assert_close(euros.value, 20.0, "10 pounds to eur");
+        let pounds = convert(10.0, eur, pound);
+        assert_eq!(pounds.units, vec![(gbp, 1)]);
+        assert_close(pounds.value, 5.0, "10 eur to pound");
+        let kgs = convert(1.0, pound, kg);
+        assert_close(kgs.value, 0.453_592_37, "1 pound to kg");
+    }
+}
@@ -1,11 +1,13 @@
 //! This has all the utility functions that rustcast uses
-use std::{fs::File, io::Write, path::Path, process::exit, thread};
+use std::{fs::File, io::Write, path::Path, process::exit, sync::LazyLock, thread};
 
 use iced::widget::image::Handle;
 use icns::IconFamily;
 use image::RgbaImage;
 use objc2_app_kit::NSWorkspace;
 use objc2_foundation::NSURL;
+use publicsuffix::{List, Psl};
+use url::{Host, Url};
 
 /// The default error log path (works only on unix systems, and must be changed for windows
 /// support)
@@ -57,6 +59,21 @@ pub fn open_settings() {
     });
 }
 
+/// Show a notification, through AppleScript
+pub fn show_notification(title: &str, body: &str) {
+    let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    let script = format!(
+        "display notification \"{}\" with title \"{}\"",
+        escape(body),
+        escape(title)
+    );
+    std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .spawn()
+        .ok();
+}
+
 /// Open a provided URL (Platform specific)
 pub fn open_url(url: &str) {
     let url = url.to_owned();
@@ -68,16 +85,172 @@ pub fn open_url(url: &str) {
     });
 }
 
-/// Check if the provided string is a valid url
-pub fn is_valid_url(s: &str) -> bool {
-    s.ends_with(".com")
-        || s.ends_with(".net")
-        || s.ends_with(".org")
-        || s.ends_with(".edu")
-        || s.ends_with(".gov")
-        || s.ends_with(".io")
-        || s.ends_with(".co")
-        || s.ends_with(".me")
-        || s.ends_with(".app")
-        || s.ends_with(".dev")
+/// The default browser, and the argument that opens a private window in it, if it has one
+///
+/// This is read from the Launch Services settings once, as it is needed for every link in the
+/// results
+pub fn private_browser() -> Option<(&'static str, &'static str)> {
+    static BROWSER: LazyLock<Option<(&str, &str)>> = LazyLock::new(|| {
+        let output = std::process::Command::new("defaults")
+            .args([
+                "read",
+                "com.apple.LaunchServices/com.apple.launchservices.secure",
+                "LSHandlers",
+            ])
+            .output()
+            .ok()?;
+        let handlers = String::from_utf8_lossy(&output.stdout).to_lowercase();
+        let handler = handlers
+            .split('}')
+            .find(|handler| handler.contains("lshandlerurlscheme = https;"))?;
+        let bundle_id = handler
+            .split("lshandlerroleall = ")
+            .nth(1)?
+            .split(';')
+            .next()?
+            .trim_matches(|c: char| c == '"' || c.is_whitespace());
+        match bundle_id {
+            "com.google.chrome" => Some(("Google Chrome", "--incognito")),
+            "com.brave.browser" => Some(("Brave Browser", "--incognito")),
+            "com.microsoft.edgemac" => Some(("Microsoft Edge", "--inprivate")),
+            "org.mozilla.firefox" => Some(("Firefox", "--private-window")),
+            _ => None,
+        }
+    });
+    *BROWSER
+}
+
+/// Percent-encode text so that it can be put into a URL
+pub fn percent_encode(text: &str) -> String {
+    url::form_urlencoded::byte_serialize(text.as_bytes()).collect()
+}
+
+/// Fill the search arguments into a search URL, in place of its `%s`
+pub fn search_url(template: &str, query: &str) -> String {
+    template.replace("%s", &percent_encode(query))
+}
+
+/// The items whose lowercase names start with the prefix, in the order of their names
+///
+/// This is how both the apps and the files are matched and ranked, so that a query finds them in
+/// the same way
+pub fn search_prefix<'a, T>(items: impl IntoIterator<Item = (&'a str, T)>, prefix: &str) -> Vec<T> {
+    let mut found = items
+        .into_iter()
+        .filter(|(name_lc, _)| name_lc.starts_with(prefix))
+        .collect::<Vec<_>>();
+    found.sort_by_key(|(name_lc, _)| *name_lc);
+    found.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Load an icon from a path in the config, which can start with `~`
+pub(crate) fn handle_from_path(path: &str) -> Option<Handle> {
+    let path = path.replace("~", &std::env::var("HOME").unwrap_or_default());
+    if path.ends_with(".icns") {
+        handle_from_icns(Path::new(&path))
+    } else {
+        Some(Handle::from_path(Path::new(&path)))
+    }
+}
+
+/// The text after an alias at the start of the query, or nothing if the query doesn't start with it
+pub fn text_after_alias<'a>(query: &'a str, alias: &str) -> &'a str {
+    let query = query.trim();
+    query
+        .get(..alias.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(alias))
+        .and_then(|_| query.get(alias.len()..))
+        .unwrap_or("")
+        .trim()
+}
+
+/// The public suffix list from <https://publicsuffix.org>, which websites are registered under
+static PUBLIC_SUFFIXES: LazyLock<List> = LazyLock::new(|| {
+    include_str!("../assets/public_suffix_list.dat")
+        .parse()
+        .unwrap_or_else(|_| List::new())
+});
+
+/// The public suffixes that are also the extensions of files, like `main.rs` or `notes.md`
+///
+/// A name under one of these is only taken as a website with a scheme, a subdomain or a path
+const FILE_EXTENSIONS: &[&str] = &[
+    "cc", "md", "ml", "mov", "pl", "pm", "ps", "py", "rs", "sh", "so", "tf", "zip",
+];
+
+/// The schemes that are opened when they are typed out, like `file:///tmp/x`
+const SCHEMES: &[&str] = &["http", "https", "file", "ftp"];
+
+/// Whether a domain is a website under a known public suffix, rather than the name of a file
+fn is_public_domain(domain: &str, has_path: bool) -> bool {
+    let Some(registrable) = PUBLIC_SUFFIXES.domain(domain.as_bytes()) else {
+        return false;
+    };
+    let suffix = registrable.suffix();
+    if !suffix.is_known() {
+        return false;
+    }
+    has_path
+        || registrable.as_bytes() != domain.as_bytes()
+        || !FILE_EXTENSIONS.contains(&String::from_utf8_lossy(suffix.as_bytes()).as_ref())
+}
+
+/// Parse a query as a URL, adding the scheme if it was left out
+///
+/// Without a scheme, the host has to be `localhost`, an IP address, one of the `intranet_hosts`
+/// (or a domain under one), or a domain under a public suffix. Local addresses get `http://` and
+/// everything else gets `https://`.
+///
+/// Names like `main.rs` also look like domains, so under the suffixes that are common file
+/// extensions a bare name needs a path (`docs.rs/serde`) or a subdomain to be a website.
+pub fn parse_url(s: &str, intranet_hosts: &[String]) -> Option<Url> {
+    let s = s.trim();
+    if s.is_empty() || s.contains(char::is_whitespace) {
+        return None;
+    }
+
+    if let Some((scheme, _)) = s.split_once("://") {
+        let url = Url::parse(s).ok()?;
+        return (SCHEMES.contains(&scheme.to_lowercase().as_str())
+            && (url.has_host() || url.scheme() == "file"))
+            .then_some(url);
+    }
+
+    // The host as it was typed, so that shorthands the URL parser accepts (`1.5` as the IP
+    // address 1.0.0.5) aren't taken as websites
+    let typed_host = s.split(['/', '?', '#']).next()?;
+    let typed_host = match typed_host.rsplit_once(':') {
+        Some((host, port)) if !host.ends_with(':') && port.chars().all(|c| c.is_ascii_digit()) => {
+            host
+        }
+        _ => typed_host,
+    };
+
+    let url = Url::parse(&format!("https://{s}")).ok()?;
+    let local = match url.host()? {
+        Host::Ipv4(ip) if ip.to_string() != typed_host => return None,
+        Host::Ipv4(_) | Host::Ipv6(_) => true,
+        Host::Domain("localhost") => true,
+        Host::Domain(domain) => {
+            let is_intranet = intranet_hosts.iter().any(|intranet| {
+                let intranet = intranet.to_lowercase();
+                domain == intranet || domain.ends_with(&format!(".{intranet}"))
+            });
+            let is_public = domain
+                .split('.')
+                .all(|label| !label.is_empty() && !label.starts_with('-'))
+                && is_public_domain(domain, s.contains(['/', '?', '#']));
+            if !is_intranet && !is_public {
+                return None;
+            }
+            false
+        }
+    };
+
+    if !local {
+        return Some(url);
+    }
+    let mut url = url;
+    url.set_scheme("http").ok()?;
+    Some(url)
 }
@@ -0,0 +1,313 @@
+//! Time zone conversion and world clock queries, like `3pm PST in Berlin`, `time in Tokyo` or
+//! `now in UTC`
+//!
+//! The IANA time zone database is bundled with rustcast (through jiff), so this works the same
+//! on every machine.
+use jiff::{
+    Zoned,
+    civil::Time,
+    tz::{self, Offset, TimeZone},
+};
+
+/// Time zone abbreviations and the zones that they are used in
+///
+/// These map to a zone rather than a fixed offset, as `3pm PST` in the summer usually means 3pm
+/// on the west coast
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("pst", "America/Los_Angeles"),
+    ("pdt", "America/Los_Angeles"),
+    ("pt", "America/Los_Angeles"),
+    ("mst", "America/Denver"),
+    ("mdt", "America/Denver"),
+    ("mt", "America/Denver"),
+    ("cst", "America/Chicago"),
+    ("cdt", "America/Chicago"),
+    ("ct", "America/Chicago"),
+    ("est", "America/New_York"),
+    ("edt", "America/New_York"),
+    ("et", "America/New_York"),
+    ("akst", "America/Anchorage"),
+    ("hst", "Pacific/Honolulu"),
+    ("bst", "Europe/London"),
+    ("wet", "Europe/Lisbon"),
+    ("cet", "Europe/Paris"),
+    ("cest", "Europe/Paris"),
+    ("eet", "Europe/Athens"),
+    ("eest", "Europe/Athens"),
+    ("msk", "Europe/Moscow"),
+    ("ist", "Asia/Kolkata"),
+    ("sgt", "Asia/Singapore"),
+    ("hkt", "Asia/Hong_Kong"),
+    ("jst", "Asia/Tokyo"),
+    ("kst", "Asia/Seoul"),
+    ("awst", "Australia/Perth"),
+    ("acst", "Australia/Adelaide"),
+    ("aest", "Australia/Sydney"),
+    ("aedt", "Australia/Sydney"),
+    ("nzst", "Pacific/Auckland"),
+    ("nzdt", "Pacific/Auckland"),
+];
+
+/// Places that aren't the name of their time zone in the database
+const PLACES: &[(&str, &str)] = &[
+    ("san francisco", "America/Los_Angeles"),
+    ("sf", "America/Los_Angeles"),
+    ("la", "America/Los_Angeles"),
+    ("seattle", "America/Los_Angeles"),
+    ("portland", "America/Los_Angeles"),
+    ("san diego", "America/Los_Angeles"),
+    ("austin", "America/Chicago"),
+    ("dallas", "America/Chicago"),
+    ("houston", "America/Chicago"),
+    ("nyc", "America/New_York"),
+    ("boston", "America/New_York"),
+    ("washington", "America/New_York"),
+    ("miami", "America/New_York"),
+    ("atlanta", "America/New_York"),
+    ("montreal", "America/Toronto"),
+    ("munich", "Europe/Berlin"),
+    ("frankfurt", "Europe/Berlin"),
+    ("hamburg", "Europe/Berlin"),
+    ("barcelona", "Europe/Madrid"),
+    ("milan", "Europe/Rome"),
+    ("geneva", "Europe/Zurich"),
+    ("edinburgh", "Europe/London"),
+    ("manchester", "Europe/London"),
+    ("st petersburg", "Europe/Moscow"),
+    ("beijing", "Asia/Shanghai"),
+    ("shenzhen", "Asia/Shanghai"),
+    ("delhi", "Asia/Kolkata"),
+    ("new delhi", "Asia/Kolkata"),
+    ("mumbai", "Asia/Kolkata"),
+    ("bangalore", "Asia/Kolkata"),
+    ("bengaluru", "Asia/Kolkata"),
+    ("hyderabad", "Asia/Kolkata"),
+    ("chennai", "Asia/Kolkata"),
+    ("osaka", "Asia/Tokyo"),
+    ("kyoto", "Asia/Tokyo"),
+    ("hanoi", "Asia/Bangkok"),
+    ("canberra", "Australia/Sydney"),
+    ("wellington", "Pacific/Auckland"),
+];
+
+/// The time somewhere, as a result row
+#[derive(Debug, Clone)]
+pub struct PlaceTime {
+    pub place: String,
+    pub time: Zoned,
+}
+
+impl PlaceTime {
+    /// The time, like `3:00 PM, Sat 18 Oct`
+    pub fn time_label(&self) -> String {
+        self.time.strftime("%-I:%M %p, %a %-d %b").to_string()
+    }
+
+    /// The place and its offset, like `Berlin · CEST (UTC+2)`
+    pub fn description(&self) -> String {
+        let abbreviation = self.time.strftime("%Z").to_string();
+        let offset = format_offset(self.time.offset());
+        if self.place == offset {
+            self.place.clone()
+        } else if abbreviation.starts_with(['+', '-']) || abbreviation == "UTC" {
+            format!("{} · {offset}", self.place)
+        } else {
+            format!("{} · {abbreviation} ({offset})", self.place)
+        }
+    }
+}
+
+/// Answer a time query, returning [`None`] if the query isn't about times at all
+///
+/// - `time` or `now` shows the local time and the time in the `favourites`
+/// - `time in <place>` and `now in <place>` show the current time there
+/// - `<time> [<zone>] in <place>` converts a time of day, like `3pm PST in Berlin`
+pub fn time_query(query: &str, favourites: &[String]) -> Option<Result<Vec<PlaceTime>, String>> {
+    let query = query.trim().to_lowercase();
+    let now = Zoned::now();
+
+    if query == "time" || query == "now" {
+        let mut results = vec![PlaceTime {
+            place: "Local".to_string(),
+            time: now.clone(),
+        }];
+        for favourite in favourites {
+            match find_zone(favourite) {
+                Some(zone) => results.push(PlaceTime {
+                    place: place_name(&favourite.trim().to_lowercase()),
+                    time: now.with_time_zone(zone),
+                }),
+                None => return Some(Err(format!("Unknown place: {favourite}"))),
+            }
+        }
+        return Some(Ok(results));
+    }
+
+    let (from, place, is_in) = match query.rsplit_once(" in ") {
+        Some((from, place)) => (from, place, true),
+        None => query
+            .rsplit_once(" to ")
+            .map(|(from, place)| (from, place, false))?,
+    };
+    let place = place.trim();
+
+    // `3 pm` is the same as `3pm`
+    let from = from.trim().replace(" am", "am").replace(" pm", "pm");
+    let time = match from.as_str() {
+        // `now to unix` is left to the date calculator
+        "time" | "now" if is_in => now,
+        from => {
+            // the time can be followed by the zone it's in, like `3pm PST`
+            let (time, zone) = match from.split_once(' ') {
+                Some((time, zone)) if parse_time(time).is_some() => (time, Some(zone)),
+                _ => (from, None),
+            };
+            let time = parse_time(time)?;
+            let zone = match zone {
+                Some(zone) => match find_zone(zone) {
+                    Some(zone) => zone,
+                    None => return Some(Err(format!("Unknown time zone: {zone}"))),
+                },
+                None => TimeZone::system(),
+            };
+
+            let today = now.with_time_zone(zone.clone()).date();
+            match today.to_datetime(time).to_zoned(zone) {
+                Ok(time) => time,
+                Err(e) => return Some(Err(e.to_string())),
+            }
+        }
+    };
+
+    let Some(zone) = find_zone(place) else {
+        return Some(Err(format!("Unknown place: {place}")));
+    };
+    Some(Ok(vec![PlaceTime {
+        place: place_name(place),
+        time: time.with_time_zone(zone),
+    }]))
+}
+
+/// Parse a time of day, like `3pm`, `3:30pm`, `15:00`, `noon` or `midnight`
+///
+/// Plain numbers aren't times, so that queries like `5 in` are left to the unit conversions
+fn parse_time(time: &str) -> Option<Time> {
+    match time {
+        "noon" | "midday" => return Some(Time::constant(12, 0, 0, 0)),
+        "midnight" => return Some(Time::MIN),
+        _ => {}
+    }
+
+    let (clock, meridiem) = match time.strip_suffix("am").or(time.strip_suffix("a.m.")) {
+        Some(clock) => (clock.trim(), Some(false)),
+        None => match time.strip_suffix("pm").or(time.strip_suffix("p.m.")) {
+            Some(clock) => (clock.trim(), Some(true)),
+            None => (time, None),
+        },
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<i8>().ok()?, minute.parse::<i8>().ok()?),
+        None if meridiem.is_some() => (clock.parse::<i8>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match meridiem {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(pm) => hour % 12 + if pm { 12 } else { 0 },
+        None => hour,
+    };
+    Time::new(hour, minute, 0, 0).ok()
+}
+
+/// Find the time zone of a place, which can be a city, an abbreviation like `PST`, an IANA name
+/// like `Europe/Berlin` or an offset like `UTC+5:30`
+pub fn find_zone(place: &str) -> Option<TimeZone> {
+    let place = place.trim().to_lowercase();
+
+    if let Some(offset) = parse_utc_offset(&place) {
+        return Some(TimeZone::fixed(offset));
+    }
+
+    let known = ABBREVIATIONS
+        .iter()
+        .chain(PLACES)
+        .find(|(name, _)| *name == place)
+        .map(|(_, zone)| *zone);
+    if let Some(zone) = known {
+        return tz::db().get(zone).ok();
+    }
+
+    // Either the whole name (`europe/berlin`) or the city in it (`new york`)
+    let name = tz::db().available().find(|name| {
+        let name = name.as_str().to_lowercase();
+        name == place
+            || name
+                .rsplit('/')
+                .next()
+                .is_some_and(|city| city.replace('_', " ") == place)
+    })?;
+    tz::db().get(name.as_str()).ok()
+}
+
+/// Parse `utc`, `gmt`, or an offset from them like `utc+2` or `gmt-5:30`
+fn parse_utc_offset(place: &str) -> Option<Offset> {
+    let offset = place
+        .strip_prefix("utc")
+        .or_else(|| place.strip_prefix("gmt"))?
+        .trim();
+    if offset.is_empty() {
+        return Some(Offset::UTC);
+    }
+
+    let (sign, offset) = if let Some(offset) = offset.strip_prefix('+') {
+        (1, offset)
+    } else {
+        (-1, offset.strip_prefix('-')?)
+    };
+    let (hours, minutes) = match offset.split_once(':') {
+        Some((hours, minutes)) => (hours.parse::<i32>().ok()?, minutes.parse::<i32>().ok()?),
+        None => (offset.parse::<i32>().ok()?, 0),
+    };
+    Offset::from_seconds(sign * (hours * 3600 + minutes * 60)).ok()
+}
+
+/// Write an offset like `UTC+2` or `UTC-9:30`
+fn format_offset(offset: Offset) -> String {
+    let seconds = offset.seconds();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let (hours, minutes) = (seconds.abs() / 3600, seconds.abs() % 3600 / 60);
+    match (hours, minutes) {
+        (0, 0) => "UTC".to_string(),
+        (hours, 0) => format!("UTC{sign}{hours}"),
+        (hours, minutes) => format!("UTC{sign}{hours}:{minutes:02}"),
+    }
+}
+
+/// How a place that the user typed in is shown, so `new york` becomes `New York` and `pst`
+/// becomes `PST`
+fn place_name(place: &str) -> String {
+    // No zone in the database is named after a place this short, so they're abbreviations
+    // like `sf` or `nyc`
+    if place.len() <= 3
+        || ABBREVIATIONS.iter().any(|(name, _)| *name == place)
+        || parse_utc_offset(place).is_some()
+    {
+        return place.to_uppercase();
+    }
+    place
+        .rsplit('/')
+        .next()
+        .unwrap_or(place)
+        .replace('_', " ")
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}